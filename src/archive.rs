@@ -0,0 +1,109 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::cli::ArchiveFormat;
+
+/// Packs `output_dir` into a single archive alongside it, e.g.
+/// `KHTX_2025_03_15` becomes `KHTX_2025_03_15.tar.gz`.
+///
+/// The archive is compressed as it is written to `<name>.tar.gz.part`, then
+/// synced and renamed into place, so the only extra space used is for the
+/// compressed output. With `remove_loose` the loose files are deleted only
+/// once the archive is complete on disk; a failed write (such as running out
+/// of space) leaves them untouched.
+pub fn archive_directory(
+    output_dir: &Path,
+    format: ArchiveFormat,
+    remove_loose: bool,
+) -> io::Result<PathBuf> {
+    let dir_name = output_dir
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "output directory has no name"))?;
+    let mut archive_name = dir_name.to_os_string();
+    archive_name.push(format.extension());
+    let archive_path = output_dir.with_file_name(&archive_name);
+    archive_name.push(".part");
+    let part_path = output_dir.with_file_name(archive_name);
+
+    let written = match format {
+        ArchiveFormat::TarGz => {
+            let encoder = GzEncoder::new(File::create(&part_path)?, Compression::default());
+            let mut builder = tar::Builder::new(encoder);
+            append_dir(&mut builder, output_dir, Path::new(dir_name))
+                .and_then(|()| builder.into_inner()?.finish())
+                .and_then(|file| file.sync_all())
+        }
+    };
+    if let Err(e) = written {
+        let _ = fs::remove_file(&part_path);
+        return Err(e);
+    }
+    fs::rename(&part_path, &archive_path)?;
+    if let Some(parent) = archive_path.parent() {
+        sync_parent(parent)?;
+    }
+
+    if remove_loose {
+        fs::remove_dir_all(output_dir)?;
+    }
+
+    Ok(archive_path)
+}
+
+fn append_dir<W: io::Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    name_in_archive: &Path,
+) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = name_in_archive.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            append_dir(builder, &path, &name)?;
+        } else if file_type.is_file() {
+            builder.append_path_with_name(&path, &name)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Flushes the rename of the finished archive, so the loose files are never
+/// deleted while the archive's directory entry could still be lost.
+fn sync_parent(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_loose_files_only_after_archive_is_in_place() {
+        let root = std::env::temp_dir().join(format!("radar-fetcher-test-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("KTST_2025_03_15");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a_V06"), b"radar").unwrap();
+
+        let archive = archive_directory(&dir, ArchiveFormat::TarGz, true).unwrap();
+
+        assert_eq!(archive, root.join("KTST_2025_03_15.tar.gz"));
+        assert!(archive.is_file());
+        assert!(!root.join("KTST_2025_03_15.tar.gz.part").exists());
+        assert!(!dir.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tokio::sync::Semaphore;
+
+/// Upper bound `--concurrency auto` will grow to.
+pub const MAX_AUTO_CONCURRENCY: usize = 256;
+
+/// A step up only sticks if it improves throughput by at least this factor.
+const MIN_IMPROVEMENT: f64 = 1.1;
+
+/// Picks a download concurrency for `--concurrency auto` from measured
+/// throughput. Starts at a conservative value, then doubles the number of
+/// download slots after each window of completed files for as long as
+/// throughput keeps improving. A step that doesn't pay off is undone, and
+/// the level before it is kept for the rest of the run.
+pub struct ConcurrencyTuner {
+    slots: Arc<Semaphore>,
+    max: usize,
+    state: Mutex<TuneState>,
+}
+
+struct TuneState {
+    current: usize,
+    /// The level before the last step up, restored if the step didn't help
+    previous: usize,
+    best_rate: f64,
+    window_started: Instant,
+    window_bytes: u64,
+    window_files: usize,
+    settled: bool,
+}
+
+impl ConcurrencyTuner {
+    pub fn new(start: usize, max: usize) -> Self {
+        let start = start.clamp(1, max);
+        Self {
+            slots: Arc::new(Semaphore::new(start)),
+            max,
+            state: Mutex::new(TuneState {
+                current: start,
+                previous: start,
+                best_rate: 0.0,
+                window_started: Instant::now(),
+                window_bytes: 0,
+                window_files: 0,
+                settled: false,
+            }),
+        }
+    }
+
+    /// The download slots to acquire from; their number changes as tuning
+    /// proceeds.
+    pub fn slots(&self) -> Arc<Semaphore> {
+        Arc::clone(&self.slots)
+    }
+
+    /// The concurrency currently in effect.
+    pub fn current(&self) -> usize {
+        self.state.lock().unwrap().current
+    }
+
+    /// Records a finished download. Once a window of as many files as there
+    /// are slots has completed, compares its throughput with the best so far
+    /// and steps the concurrency up, or settles.
+    pub fn record(&self, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.settled {
+            return;
+        }
+        state.window_bytes += bytes;
+        state.window_files += 1;
+        if state.window_files < state.current {
+            return;
+        }
+
+        let elapsed = state.window_started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let rate = state.window_bytes as f64 / elapsed;
+        if rate >= state.best_rate * MIN_IMPROVEMENT && state.current < self.max {
+            let next = (state.current * 2).min(self.max);
+            self.slots.add_permits(next - state.current);
+            state.best_rate = rate;
+            state.previous = state.current;
+            state.current = next;
+        } else {
+            if rate < state.best_rate && state.previous < state.current {
+                // Take the extra slots back as downloads release them
+                let excess = (state.current - state.previous) as u32;
+                let slots = Arc::clone(&self.slots);
+                tokio::spawn(async move {
+                    if let Ok(permits) = slots.acquire_many_owned(excess).await {
+                        permits.forget();
+                    }
+                });
+                state.current = state.previous;
+            }
+            state.settled = true;
+        }
+        state.window_started = Instant::now();
+        state.window_bytes = 0;
+        state.window_files = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_window_steps_up() {
+        let tuner = ConcurrencyTuner::new(2, 8);
+        tuner.record(100);
+        assert_eq!(tuner.current(), 2);
+        tuner.record(100);
+        assert_eq!(tuner.current(), 4);
+        assert_eq!(tuner.slots().available_permits(), 4);
+    }
+
+    #[test]
+    fn settles_at_the_cap() {
+        let tuner = ConcurrencyTuner::new(4, 4);
+        for _ in 0..4 {
+            tuner.record(100);
+        }
+        assert_eq!(tuner.current(), 4);
+        assert_eq!(tuner.slots().available_permits(), 4);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// The lowercase hex SHA-256 of `bytes`, as used for object names.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().fold(String::with_capacity(64), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Name of the name→hash index kept at the root of the store.
+pub const INDEX_FILE: &str = "index.tsv";
+
+/// Stores files under `ROOT/ab/cdef...`, named by the SHA-256 of their
+/// content, so identical files share one object across runs. Each stored
+/// file appends a `HASH<TAB>FILENAME<TAB>URL` line to `ROOT/index.tsv`.
+pub struct ContentStore {
+    root: PathBuf,
+    /// Serialises appends so index lines never interleave
+    index: Mutex<()>,
+    next_temp: AtomicU64,
+}
+
+impl ContentStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root, index: Mutex::new(()), next_temp: AtomicU64::new(0) }
+    }
+
+    /// Stores `bytes`, whose [`sha256_hex`] is `hash`, unless an object with
+    /// the same hash already exists, then records `filename` and `url`
+    /// against it.
+    pub async fn put(&self, filename: &str, url: &str, bytes: &[u8], hash: &str, fsync: bool) -> io::Result<()> {
+        let object = self.object_path(hash);
+        let shard = object.parent().expect("object paths have a shard directory");
+
+        if fs::metadata(&object).await.is_err() {
+            fs::create_dir_all(shard).await?;
+            // Write under a unique name and rename, so a concurrent download of
+            // the same content never sees a half-written object
+            let temp = shard.join(format!(
+                ".{}.{}.{}",
+                &hash[2..],
+                std::process::id(),
+                self.next_temp.fetch_add(1, Ordering::Relaxed)
+            ));
+            let mut file = File::create(&temp).await?;
+            file.write_all(bytes).await?;
+            if fsync {
+                file.sync_all().await?;
+            }
+            drop(file);
+            fs::rename(&temp, &object).await?;
+        }
+        if fsync {
+            // Make the object's and shard's directory entries durable before
+            // the index can point at them. This also covers objects another
+            // download has just renamed into place but not yet synced.
+            crate::sync_dir(shard).await?;
+            crate::sync_dir(&self.root).await?;
+        }
+
+        let _guard = self.index.lock().await;
+        let index_path = self.root.join(INDEX_FILE);
+        let new_index = fs::metadata(&index_path).await.is_err();
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index_path)
+            .await?;
+        index.write_all(format!("{}\t{}\t{}\n", hash, filename, url).as_bytes()).await?;
+        if fsync {
+            index.sync_all().await?;
+            if new_index {
+                crate::sync_dir(&self.root).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(&hash[2..])
+    }
+}
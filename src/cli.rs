@@ -0,0 +1,412 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand, ValueEnum};
+use url::Url;
+
+/// NOAA's index endpoint for NEXRAD archive downloads.
+pub const DEFAULT_BASE_URL: &str = "https://www.ncdc.noaa.gov/nexradinv/bdp-download.jsp";
+
+/// Downloads NEXRAD Level II radar data from NOAA's archive.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+#[command(group = clap::ArgGroup::new("destination").args(["output_dir", "cas"]))]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Index endpoint to query; the site, date and product are added as query parameters
+    #[arg(long, value_name = "URL", default_value = DEFAULT_BASE_URL, value_parser = parse_base_url)]
+    pub base_url: Url,
+
+    /// Accept header to send when fetching the index, for endpoints that negotiate the listing format
+    #[arg(long, value_name = "MEDIA_TYPES", conflicts_with = "links_file")]
+    pub accept: Option<String>,
+
+    /// Number of concurrent downloads, or `auto` to start from the machine's core count and tune from measured throughput
+    #[arg(long, default_value = "50")]
+    pub concurrency: Concurrency,
+
+    /// How many download tasks to keep queued at once (defaults to --concurrency); bounds memory on huge link sets
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub task_buffer: Option<u32>,
+
+    /// Download the URLs listed in FILE (one per line, `-` for stdin) instead of scraping the index
+    #[arg(long, value_name = "FILE", requires = "destination", conflicts_with_all = ["probe_date_availability", "reconcile"])]
+    pub links_file: Option<String>,
+
+    /// Directory to save files in, instead of one named after the site and date
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<String>,
+
+    /// Store files in a content-addressed tree under DIR (`DIR/ab/cdef...`), recording names in DIR/index.tsv
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = [
+            "touch_only", "reconcile", "normalize_output", "per_file_metadata",
+            "decode_command", "archive_on_complete", "with_reports",
+        ],
+    )]
+    pub cas: Option<String>,
+
+    /// Honor each host's robots.txt, skipping links it disallows (meant for third-party mirrors)
+    #[arg(long)]
+    pub respect_robots: bool,
+
+    /// Download links host by host, in order of each host's first link, to reuse connections
+    #[arg(long)]
+    pub batch_by_host: bool,
+
+    /// Download files in the order their names are listed in FILE (one per line); unlisted links go last
+    #[arg(long, value_name = "FILE", conflicts_with = "batch_by_host")]
+    pub download_order_file: Option<String>,
+
+    /// Skip links not named in --download-order-file instead of downloading them last
+    #[arg(long, requires = "download_order_file")]
+    pub drop_unlisted: bool,
+
+    /// Skip every link up to and including this URL
+    #[arg(long, value_name = "URL", conflicts_with = "resume_from_file")]
+    pub resume_from_url: Option<String>,
+
+    /// Skip every link up to and including the one saved under this filename
+    #[arg(long, value_name = "NAME")]
+    pub resume_from_file: Option<String>,
+
+    /// Describe what the run would do, then exit without doing it
+    #[arg(long, conflicts_with = "probe_date_availability")]
+    pub explain: bool,
+
+    /// Check the site, date, index, output directory and free space, then exit 0 if all pass or 1 if any fail
+    #[arg(long, conflicts_with_all = ["explain", "probe_date_availability", "dump_links"])]
+    pub preflight: bool,
+
+    /// Print the discovered download URLs, one per line, and exit without downloading
+    #[arg(long)]
+    pub dump_links: bool,
+
+    /// Set existing files' mtimes to the server's Last-Modified instead of downloading
+    #[arg(long)]
+    pub touch_only: bool,
+
+    /// How --touch-only asks the server for a file's size and date
+    #[arg(long, value_enum, default_value_t = ProbeMethod::Auto, requires = "touch_only")]
+    pub probe_method: ProbeMethod,
+
+    /// Never have more than this many requests in flight, across every phase of the run
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_connections_total: Option<u32>,
+
+    /// Redraw the progress line at most this many times per second
+    #[arg(long, value_name = "PER_SECOND", value_parser = parse_refresh_rate)]
+    pub progress_refresh_rate: Option<f64>,
+
+    /// Refuse TLS connections below this version.
+    ///
+    /// `1.3` switches to the rustls TLS stack, which still trusts the system's
+    /// certificate store.
+    #[arg(long, value_enum, value_name = "VERSION")]
+    pub strict_tls_version: Option<TlsVersion>,
+
+    /// Report how many files each date in START..END has, without downloading
+    #[arg(long, value_name = "START..END")]
+    pub probe_date_availability: Option<DateRange>,
+
+    /// Label the run with this, e.g. a pipeline run ID; recorded in the summary, sidecars and metrics
+    #[arg(long, value_name = "STRING")]
+    pub tag: Option<String>,
+
+    /// Format for reports printed to stdout
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub output_format: OutputFormat,
+
+    /// How to print the end-of-run summary
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Human)]
+    pub summary_format: SummaryFormat,
+
+    /// Write the run's totals to PATH in Prometheus text format, for node_exporter's textfile collector
+    #[arg(long, value_name = "PATH")]
+    pub metrics_file: Option<PathBuf>,
+
+    /// After downloading, re-fetch the index and report files missing locally or remotely
+    #[arg(long)]
+    pub reconcile: bool,
+
+    /// Rename downloaded files so their extensions match their content (.gz, .bz2, .tar)
+    #[arg(long)]
+    pub normalize_output: bool,
+
+    /// Write a <filename>.json next to each download with its URL, size, timestamps and ETag
+    #[arg(long, conflicts_with = "normalize_output")]
+    pub per_file_metadata: bool,
+
+    /// Run `COMMAND INPUT OUTPUT` on each downloaded file to produce a derived product
+    #[arg(long, value_name = "COMMAND")]
+    pub decode_command: Option<String>,
+
+    /// Extension appended to a file's name to form the decoder's OUTPUT path
+    #[arg(long, value_name = "EXT", default_value = "decoded", requires = "decode_command")]
+    pub decode_extension: String,
+
+    /// Delete each raw file once it has been decoded successfully
+    #[arg(long, requires = "decode_command", conflicts_with = "normalize_output")]
+    pub discard_raw: bool,
+
+    /// Break down bytes and transfer time by host in the summary
+    #[arg(long)]
+    pub report_bandwidth_per_host: bool,
+
+    /// Also save the day's SPC storm reports CSV into a reports/ subdirectory
+    #[arg(long, conflicts_with = "links_file")]
+    pub with_reports: bool,
+
+    /// Pack the output directory into a single archive once downloads finish
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub archive_on_complete: Option<ArchiveFormat>,
+
+    /// Delete the loose files after --archive-on-complete has packed them
+    #[arg(long, requires = "archive_on_complete")]
+    pub remove_after_archive: bool,
+
+    /// Refuse dates older than this, e.g. `7d`, to guard against mistyped years
+    #[arg(long, value_name = "AGE", value_parser = parse_duration, conflicts_with = "links_file")]
+    pub max_age: Option<Duration>,
+
+    /// Whether a date older than --max-age stops the run or only warns
+    #[arg(long, value_enum, default_value_t = StalePolicy::Error, requires = "max_age")]
+    pub on_stale: StalePolicy,
+
+    /// Give up on any single file whose transfer takes longer than this, e.g. `90s` or `10m`
+    #[arg(long, value_parser = parse_duration)]
+    pub file_deadline: Option<Duration>,
+
+    /// Limit how many downloads write to disk at once, for slow disks such as network storage.
+    ///
+    /// Files that have finished downloading wait in memory for a write slot, so
+    /// memory use can reach --concurrency times the size of a file.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub write_concurrency: Option<u32>,
+
+    /// Pause (or abort, see --on-low-space) when free space on the output volume drops below this, e.g. `5GB`
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_free_space: Option<u64>,
+
+    /// What to do when free space drops below --min-free-space
+    #[arg(long, value_enum, default_value_t = LowSpacePolicy::Pause, requires = "min_free_space")]
+    pub on_low_space: LowSpacePolicy,
+
+    /// If the network drops mid-run, pause for up to this long (e.g. `10m`) waiting for it to return
+    #[arg(long, value_name = "MAX_WAIT", value_parser = parse_duration)]
+    pub wait_for_network: Option<Duration>,
+
+    /// Save empty responses as zero-byte files instead of counting them as failures
+    #[arg(long)]
+    pub allow_empty: bool,
+
+    /// Sync each file and the output directory to disk after writing (slower, but survives power loss)
+    #[arg(long)]
+    pub fsync: bool,
+}
+
+fn parse_refresh_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!("expected a positive number of redraws per second, got `{}`", s)),
+    }
+}
+
+fn parse_base_url(s: &str) -> Result<Url, String> {
+    let url = Url::parse(s).map_err(|e| format!("invalid URL `{}`: {}", s, e))?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        scheme => Err(format!("base URL must be http or https, not {}", scheme)),
+    }
+}
+
+/// Parses sizes such as `500MB`, `5GB` or `1.5TB` (powers of 1024). A bare
+/// number is taken as bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("expected a size like `500MB` or `5GB`, got `{}`", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(format!("unknown size unit `{}` (use B, KB, MB, GB or TB)", unit)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses durations such as `45`, `45s`, `10m`, `2h` or `7d`. A bare number
+/// is taken as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a duration like `90s` or `10m`, got `{}`", s))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown duration unit `{}` (use s, m, h or d)", unit)),
+    };
+    Ok(Duration::from_secs(number * multiplier))
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check this build by fetching from a built-in test server instead of NOAA
+    SelfTest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ArchiveFormat {
+    #[value(name = "tar.gz")]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => ".tar.gz",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LowSpacePolicy {
+    /// Hold new downloads until space is freed
+    Pause,
+    /// Fail the remaining downloads
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProbeMethod {
+    /// HEAD, switching to a one-byte GET if the server rejects HEAD with 405
+    Auto,
+    Head,
+    /// A GET for only the first byte
+    Get,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StalePolicy {
+    /// Print a warning and carry on
+    Warn,
+    /// Exit without downloading
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TlsVersion {
+    #[value(name = "1.2")]
+    Tls12,
+    #[value(name = "1.3")]
+    Tls13,
+}
+
+impl From<TlsVersion> for reqwest::tls::Version {
+    fn from(version: TlsVersion) -> Self {
+        match version {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SummaryFormat {
+    Human,
+    /// A single greppable line, suited to cron mail
+    Compact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Concurrency {
+    Auto,
+    Fixed(usize),
+}
+
+impl Concurrency {
+    /// Resolves `auto` to a starting value based on available parallelism.
+    /// Downloads are network-bound, so this scales well past the core count;
+    /// `autotune::ConcurrencyTuner` raises it from there while throughput
+    /// keeps improving.
+    pub fn resolve(self) -> usize {
+        match self {
+            Concurrency::Fixed(n) => n,
+            Concurrency::Auto => {
+                let cpus = std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1);
+                (cpus * 8).clamp(8, 64)
+            }
+        }
+    }
+}
+
+impl FromStr for Concurrency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Concurrency::Auto);
+        }
+        match s.parse::<usize>() {
+            Ok(0) => Err("concurrency must be at least 1".to_string()),
+            Ok(n) => Ok(Concurrency::Fixed(n)),
+            Err(_) => Err(format!("expected a number or `auto`, got `{}`", s)),
+        }
+    }
+}
+
+/// An inclusive range of dates, written as `2025-03-01..2025-03-15`.
+#[derive(Debug, Clone, Copy)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.end;
+        self.start.iter_days().take_while(move |day| *day <= end)
+    }
+}
+
+impl FromStr for DateRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| format!("expected START..END, got `{}`", s))?;
+        let parse = |date: &str| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|e| format!("invalid date `{}`: {}", date, e))
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if end < start {
+            return Err(format!("range ends ({}) before it starts ({})", end, start));
+        }
+        Ok(DateRange { start, end })
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::OnceLock;
+
+use reqwest::ClientBuilder;
+use reqwest::tls::Version;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+static CONNECTIONS: OnceLock<Semaphore> = OnceLock::new();
+static MIN_TLS_VERSION: OnceLock<Version> = OnceLock::new();
+
+/// Caps the number of outbound requests in flight across the whole process.
+/// Has no effect if called more than once.
+pub fn set_max_connections(max: usize) {
+    let _ = CONNECTIONS.set(Semaphore::new(max));
+}
+
+/// Waits for a connection slot if `--max-connections-total` is set. Hold the
+/// permit until the response body has been read.
+pub async fn acquire() -> Option<SemaphorePermit<'static>> {
+    match CONNECTIONS.get() {
+        Some(semaphore) => Some(semaphore.acquire().await.expect("connection semaphore closed")),
+        None => None,
+    }
+}
+
+/// Rejects connections that negotiate a TLS version older than `version` for
+/// every client built afterwards. Has no effect if called more than once.
+pub fn set_min_tls_version(version: Version) {
+    let _ = MIN_TLS_VERSION.set(version);
+}
+
+/// Starts building an HTTP client with the process-wide connection policy
+/// applied. Every client should come from here.
+pub fn client_builder() -> ClientBuilder {
+    let builder = ClientBuilder::new();
+    match MIN_TLS_VERSION.get() {
+        // The native-tls backend can't require 1.3, so use rustls for it. It
+        // loads the platform's root certificates, so private CAs still work
+        Some(&version) if version == Version::TLS_1_3 => builder.use_rustls_tls().min_tls_version(version),
+        Some(&version) => builder.min_tls_version(version),
+        None => builder,
+    }
+}
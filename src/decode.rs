@@ -0,0 +1,52 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Turns a downloaded file into a derived product, such as extracted
+/// reflectivity. Runs on a blocking thread once the raw file is on disk.
+pub trait Decoder: Send + Sync {
+    /// Decodes the file at `input` and returns the path of the derived output.
+    fn decode(&self, input: &Path) -> io::Result<PathBuf>;
+}
+
+/// Runs an external program as `PROGRAM [ARGS...] INPUT OUTPUT`, where OUTPUT
+/// is the input path with `extension` appended.
+pub struct CommandDecoder {
+    program: String,
+    args: Vec<String>,
+    extension: String,
+}
+
+impl CommandDecoder {
+    /// Builds a decoder from a whitespace-separated command line.
+    pub fn new(command: &str, extension: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "decode command is empty"))?;
+        Ok(Self {
+            program,
+            args: words.collect(),
+            extension: extension.trim_start_matches('.').to_string(),
+        })
+    }
+}
+
+impl Decoder for CommandDecoder {
+    fn decode(&self, input: &Path) -> io::Result<PathBuf> {
+        let mut output = input.as_os_str().to_os_string();
+        output.push(".");
+        output.push(&self.extension);
+        let output = PathBuf::from(output);
+
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(input)
+            .arg(&output)
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("{} exited with {}", self.program, status)));
+        }
+        Ok(output)
+    }
+}
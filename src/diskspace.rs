@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use crate::cli::LowSpacePolicy;
+use crate::summary::format_bytes;
+
+/// How often free space is re-checked while paused.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Checks free space on the output volume before each download starts, so
+/// another process filling the disk doesn't turn into a run of write errors.
+pub struct DiskGuard {
+    dir: PathBuf,
+    min_free: u64,
+    policy: LowSpacePolicy,
+    aborted: AtomicBool,
+    /// Held while paused, so only one download polls and logs
+    paused: Mutex<()>,
+}
+
+impl DiskGuard {
+    pub fn new(dir: PathBuf, min_free: u64, policy: LowSpacePolicy) -> Self {
+        Self {
+            dir,
+            min_free,
+            policy,
+            aborted: AtomicBool::new(false),
+            paused: Mutex::new(()),
+        }
+    }
+
+    fn available(&self) -> std::io::Result<u64> {
+        fs2::available_space(&self.dir)
+    }
+
+    /// Returns once there is enough free space, or fails if the policy is to
+    /// abort. While paused, every caller waits here.
+    pub async fn wait_for_space(&self) -> Result<(), String> {
+        let _paused = self.paused.lock().await;
+        if self.aborted.load(Ordering::SeqCst) {
+            return Err("run aborted: free space fell below --min-free-space".to_string());
+        }
+
+        let mut available = self.available().map_err(|e| e.to_string())?;
+        if available >= self.min_free {
+            return Ok(());
+        }
+
+        match self.policy {
+            LowSpacePolicy::Abort => {
+                self.aborted.store(true, Ordering::SeqCst);
+                eprintln!(
+                    "\nOnly {} free in {}, below the {} minimum; aborting remaining downloads",
+                    format_bytes(available),
+                    self.dir.display(),
+                    format_bytes(self.min_free)
+                );
+                Err("free space fell below --min-free-space".to_string())
+            }
+            LowSpacePolicy::Pause => {
+                eprintln!(
+                    "\nOnly {} free in {}, below the {} minimum; pausing until space is freed",
+                    format_bytes(available),
+                    self.dir.display(),
+                    format_bytes(self.min_free)
+                );
+                while available < self.min_free {
+                    tokio::time::sleep(RECHECK_INTERVAL).await;
+                    available = self.available().map_err(|e| e.to_string())?;
+                }
+                eprintln!("{} free again; resuming downloads", format_bytes(available));
+                Ok(())
+            }
+        }
+    }
+}
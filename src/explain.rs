@@ -0,0 +1,169 @@
+use serde_json::{Value, json};
+
+use crate::cli::{Args, Concurrency, LowSpacePolicy, OutputFormat};
+use crate::summary::format_bytes;
+
+/// Where a run's links come from.
+pub enum Source<'a> {
+    Index { site: &'a str, date: &'a str, url: &'a str },
+    LinksFile(&'a str),
+}
+
+/// Describes what a run with `args` would do, without doing any of it.
+pub fn explain(args: &Args, source: &Source, output_dir: &str) {
+    let concurrency = args.concurrency.resolve();
+    let mode = if args.dump_links {
+        "print the discovered links and exit"
+    } else if args.touch_only {
+        "update mtimes of existing files from Last-Modified, without downloading"
+    } else {
+        "download files"
+    };
+    let resume = match (&args.resume_from_url, &args.resume_from_file) {
+        (Some(url), _) => Some(format!("skip links up to and including {}", url)),
+        (_, Some(name)) => Some(format!("skip links up to and including the one saved as {}", name)),
+        _ => None,
+    };
+    let robots = args.respect_robots.then_some(match source {
+        Source::Index { .. } => "skip links robots.txt disallows, and stop if it disallows the index",
+        Source::LinksFile(_) => "skip links robots.txt disallows",
+    });
+    let drop_unlisted = args
+        .download_order_file
+        .as_ref()
+        .filter(|_| args.drop_unlisted)
+        .map(|path| format!("skip links not named in {}", path));
+    let filters: Vec<&str> = ["only http/https links"]
+        .into_iter()
+        .chain(robots)
+        .chain(resume.as_deref())
+        .chain(drop_unlisted.as_deref())
+        .collect();
+    let order = match (&args.download_order_file, args.batch_by_host) {
+        (Some(path), _) if args.drop_unlisted => format!("the order names are listed in {}", path),
+        (Some(path), _) => format!("the order names are listed in {}, then unlisted links", path),
+        (None, true) => "host by host, in order of each host's first link".to_string(),
+        (None, false) => "as listed".to_string(),
+    };
+    let after_download: Vec<String> = [
+        args.decode_command.as_ref().map(|command| {
+            let discard = if args.discard_raw { ", then delete the raw file" } else { "" };
+            format!("decode each file with `{}` into *.{}{}", command, args.decode_extension, discard)
+        }),
+        args.with_reports.then(|| "save the day's SPC storm reports to reports/".to_string()),
+        args.reconcile.then(|| "re-fetch the index and reconcile it against the directory".to_string()),
+        args.normalize_output.then(|| "fix file extensions from their content".to_string()),
+        args.archive_on_complete.map(|format| {
+            let remove = if args.remove_after_archive { ", removing the loose files" } else { "" };
+            format!("pack the directory into {}{}{}", output_dir, format.extension(), remove)
+        }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    match args.output_format {
+        OutputFormat::Human => {
+            println!("This run would {}.", mode);
+            if let Some(tag) = &args.tag {
+                println!("  Tag: {}", tag);
+            }
+            match source {
+                Source::Index { site, date, url } => {
+                    println!("  Source: NOAA index for {} on {}, product {}", site, date, crate::PRODUCT);
+                    println!("          {}", url);
+                }
+                Source::LinksFile(path) => println!("  Source: URLs listed in {}", path),
+            }
+            println!("  Filters: {}", filters.join("; "));
+            println!("  Order: {}", order);
+            println!("  Output: {}{}", output_dir, if args.fsync { " (fsync after each file)" } else { "" });
+            if args.cas.is_some() {
+                println!(
+                    "  Layout: content-addressed, each file at {}/ab/cdef... by SHA-256, names recorded in {}",
+                    output_dir,
+                    crate::cas::INDEX_FILE
+                );
+            } else {
+                println!("  Layout: flat, one file per link named after its URL");
+            }
+            match args.concurrency {
+                Concurrency::Auto => println!(
+                    "  Concurrency: starting at {} files at once, tuned up to {} from measured throughput (auto)",
+                    concurrency,
+                    crate::autotune::MAX_AUTO_CONCURRENCY
+                ),
+                Concurrency::Fixed(_) => println!("  Concurrency: {} files at once", concurrency),
+            }
+            if let Some(n) = args.write_concurrency {
+                println!("               {} writing to disk at once", n);
+            }
+            if let Some(n) = args.max_connections_total {
+                println!("               at most {} requests in flight overall", n);
+            }
+            if let Some(deadline) = args.file_deadline {
+                println!("  Per-file deadline: {}s", deadline.as_secs());
+            }
+            if let Some(min_free) = args.min_free_space {
+                let action = match args.on_low_space {
+                    LowSpacePolicy::Pause => "pause",
+                    LowSpacePolicy::Abort => "abort",
+                };
+                println!("  Low disk space: {} below {} free", action, format_bytes(min_free));
+            }
+            if let Some(wait) = args.wait_for_network {
+                println!("  Network loss: wait up to {}s for it to return", wait.as_secs());
+            }
+            for step in &after_download {
+                println!("  Afterwards: {}", step);
+            }
+        }
+        OutputFormat::Json => {
+            let source = match source {
+                Source::Index { site, date, url } => json!({
+                    "type": "index",
+                    "site": site,
+                    "date": date,
+                    "product": crate::PRODUCT,
+                    "url": url,
+                }),
+                Source::LinksFile(path) => json!({ "type": "links_file", "path": path }),
+            };
+            let plan = json!({
+                "mode": mode,
+                "tag": args.tag,
+                "source": source,
+                "filters": {
+                    "schemes": ["http", "https"],
+                    "robots_txt": args.respect_robots,
+                    "resume": resume,
+                    "drop_unlisted": args.drop_unlisted,
+                },
+                "order": match (&args.download_order_file, args.batch_by_host) {
+                    (Some(path), _) => json!({ "type": "order_file", "path": path }),
+                    (None, true) => json!({ "type": "by_host" }),
+                    (None, false) => json!({ "type": "as_listed" }),
+                },
+                "output_dir": output_dir,
+                "layout": if args.cas.is_some() {
+                    json!({ "type": "content_addressed", "hash": "sha256", "index": crate::cas::INDEX_FILE })
+                } else {
+                    json!({ "type": "flat" })
+                },
+                "fsync": args.fsync,
+                "concurrency": {
+                    "files": concurrency,
+                    "auto": matches!(args.concurrency, Concurrency::Auto),
+                    "auto_max": matches!(args.concurrency, Concurrency::Auto).then_some(crate::autotune::MAX_AUTO_CONCURRENCY),
+                    "writes": args.write_concurrency,
+                    "connections_total": args.max_connections_total,
+                },
+                "file_deadline_secs": args.file_deadline.map(|d| d.as_secs()),
+                "wait_for_network_secs": args.wait_for_network.map(|d| d.as_secs()),
+                "min_free_space_bytes": args.min_free_space,
+                "after_download": Value::from(after_download),
+            });
+            println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use url::Url;
+
+use crate::{filename_from_url, is_supported_scheme, name_clashes, names_a_file};
+
+/// Reads download URLs from `path`, or from stdin when `path` is `-`.
+///
+/// One URL per line; blank lines and lines starting with `#` are ignored.
+pub fn read_links(path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if path == "-" {
+        parse_links(io::stdin().lock(), "stdin")
+    } else {
+        parse_links(BufReader::new(File::open(path)?), path)
+    }
+}
+
+fn parse_links(reader: impl BufRead, source: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut links = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let url = Url::parse(line)
+            .map_err(|e| format!("{} line {}: invalid URL `{}`: {}", source, number + 1, line, e))?;
+        let url = url.to_string();
+        if !is_supported_scheme(&url) {
+            eprintln!("Skipping {}: only http and https downloads are supported", url);
+            continue;
+        }
+        if !names_a_file(&url) {
+            eprintln!("Skipping {}: URL ends in a directory, not a file", url);
+            continue;
+        }
+        if links.contains(&url) {
+            continue;
+        }
+        if name_clashes(&url, &links) {
+            eprintln!("Skipping {}: another link is already saved as {}", url, filename_from_url(&url));
+            continue;
+        }
+        links.push(url);
+    }
+    Ok(links)
+}
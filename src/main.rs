@@ -1,63 +1,96 @@
+mod archive;
+mod autotune;
+mod cas;
+mod cli;
+mod connections;
+mod decode;
+mod diskspace;
+mod explain;
+mod links_file;
+mod metrics;
+mod network;
+mod normalize;
+mod preflight;
+mod probe;
+mod progress;
+mod reconcile;
+mod reports;
+mod robots;
+mod selftest;
+mod summary;
+mod touch;
+
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use futures::future::join_all;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use chrono::NaiveDate;
+use autotune::ConcurrencyTuner;
+use cas::ContentStore;
+use clap::Parser;
+use decode::{CommandDecoder, Decoder};
+use diskspace::DiskGuard;
+use network::{AfterFailure, NetworkGate};
+use futures::stream::{self, StreamExt};
+use progress::{ConsoleProgress, Progress};
+use robots::RobotsCache;
 use reqwest::Client;
+use reqwest::header::{ACCEPT, ETAG, HeaderName, LAST_MODIFIED};
 use scraper::{Html, Selector};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
 use url::Url;
 
-#[derive(Debug)]
-struct DownloadProgress {
-    total: usize,
-    current: AtomicUsize,
-    last_file: Arc<Mutex<String>>,
-}
+/// Product code requested from the index: Level II base data.
+const PRODUCT: &str = "AAL2";
 
-impl DownloadProgress {
-    fn new(total: usize) -> Self {
-        Self {
-            total,
-            current: AtomicUsize::new(0),
-            last_file: Arc::new(Mutex::new(String::new())),
-        }
-    }
+/// Accept header for index requests when `--accept` isn't given. AAL2
+/// listings are HTML pages, some with the file list embedded as JSON.
+const DEFAULT_INDEX_ACCEPT: &str = "text/html,application/xhtml+xml;q=0.9,application/json;q=0.8,*/*;q=0.5";
 
-    fn increment(&self, filename: &str) {
-        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
-        {
-            let mut last_file = self.last_file.lock().unwrap();
-            *last_file = filename.to_string();
-        }
-        self.print_progress(current, filename);
-    }
+/// Accept header sent with index requests, set once from `--accept`.
+static INDEX_ACCEPT: OnceLock<String> = OnceLock::new();
 
-    fn print_progress(&self, current: usize, filename: &str) {
-        let percentage = (current as f64 / self.total as f64) * 100.0;
-        print!("\rDownloading Files: {}/{} ({:.1}%) | Last: {} ",
-               current, self.total, percentage, filename);
-        io::stdout().flush().unwrap();
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (HTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
 
-        if current == self.total {
-            println!("\nDownload Complete!");
-        }
-    }
+/// Builds the index page URL for one site and day from the `bdp-download.jsp`
+/// endpoint (see `cli::DEFAULT_BASE_URL`).
+fn index_url(base_url: &Url, radar: &str, year: &str, month: &str, day: &str) -> String {
+    let mut url = base_url.clone();
+    url.query_pairs_mut()
+        .append_pair("id", radar)
+        .append_pair("yyyy", year)
+        .append_pair("mm", month)
+        .append_pair("dd", day)
+        .append_pair("product", PRODUCT);
+    url.to_string()
 }
 
-async fn fetch_download_links(radar_url: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (HTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
+async fn fetch_index(radar_url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let client = connections::client_builder()
+        .user_agent(USER_AGENT)
         .build()?;
-    let response = client.get(radar_url).send().await?;
-    let html = response.text().await?;
-
-    println!("Fetching from URL: {}", radar_url);
+    let _connection = connections::acquire().await;
+    let accept = INDEX_ACCEPT.get().map_or(DEFAULT_INDEX_ACCEPT, String::as_str);
+    let response = client.get(radar_url).header(ACCEPT, accept).send().await?;
+    Ok(response.text().await?)
+}
 
-    let document = Html::parse_document(&html);
+struct ExtractedLinks {
+    links: Vec<String>,
+    /// The selector that matched, if any did
+    selector: Option<&'static str>,
+    /// Links dropped because we can't download over their scheme
+    unsupported: Vec<String>,
+    /// Links dropped because an earlier link is saved under the same name
+    clashing: Vec<String>,
+}
 
+/// Pulls data file links out of an index page.
+fn extract_links(radar_url: &str, document: &Html) -> Result<ExtractedLinks, Box<dyn std::error::Error>> {
     // Try multiple selectors that might contain download links
     let selectors = vec![
         "div.bdpLink a",
@@ -74,32 +107,168 @@ async fn fetch_download_links(radar_url: &str) -> Result<Vec<String>, Box<dyn st
     ];
 
     let mut links = Vec::new();
+    let mut unsupported = Vec::new();
+    let mut clashing = Vec::new();
 
-    for selector_str in &selectors {
+    for selector_str in selectors {
         if let Ok(selector) = Selector::parse(selector_str) {
             let mut found_links = 0;
             for element in document.select(&selector) {
-                if let Some(href) = element.value().attr("href") {
-                    // Filter for likely data file links
-                    if href.contains(".gz") ||
-                        href.contains(".tar") ||
-                        href.contains(".bz2") ||
-                        href.contains("V06") ||
-                        href.contains("AAL2") ||
-                        (href.starts_with("http") && href.contains("download")) {
-                        let absolute_url = resolve_url(radar_url, href)?;
-                        if !links.contains(&absolute_url) {
-                            links.push(absolute_url);
-                            found_links += 1;
-                        }
-                    }
+                if let Some(href) = element.value().attr("href")
+                    && collect_link(radar_url, href, &mut links, &mut unsupported, &mut clashing)? {
+                    found_links += 1;
                 }
             }
             if found_links > 0 {
-                println!("Found {} links using selector: {}", found_links, selector_str);
-                break; // Use the first selector that finds links
+                // Use the first selector that finds links
+                return Ok(ExtractedLinks { links, selector: Some(selector_str), unsupported, clashing });
+            }
+        }
+    }
+
+    // Some pages render their listing from a JSON blob in a <script> instead
+    // of plain <a> tags
+    let mut found_links = 0;
+    for value in json_data_islands(document) {
+        let mut strings = Vec::new();
+        collect_json_strings(&value, &mut strings);
+        // Any string in the blob could be a product code or page path, so
+        // only ones naming a data file count
+        for href in strings.into_iter().filter(|href| names_data_file(href)) {
+            if collect_link(radar_url, href, &mut links, &mut unsupported, &mut clashing)? {
+                found_links += 1;
+            }
+        }
+    }
+    if found_links > 0 {
+        return Ok(ExtractedLinks { links, selector: Some(JSON_DATA_SELECTOR), unsupported, clashing });
+    }
+
+    Ok(ExtractedLinks { links, selector: None, unsupported, clashing })
+}
+
+/// Adds `href` to `links` if it looks like a data file, returning whether it
+/// was new. Links over schemes we can't download go to `unsupported` instead,
+/// and links that would be saved under an existing link's name to `clashing`.
+fn collect_link(
+    radar_url: &str,
+    href: &str,
+    links: &mut Vec<String>,
+    unsupported: &mut Vec<String>,
+    clashing: &mut Vec<String>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // Filter for likely data file links
+    if !(href.contains(".gz") ||
+        href.contains(".tar") ||
+        href.contains(".bz2") ||
+        href.contains("V06") ||
+        href.contains("AAL2") ||
+        (href.starts_with("http") && href.contains("download"))) {
+        return Ok(false);
+    }
+    let absolute_url = resolve_url(radar_url, href)?;
+    if !is_supported_scheme(&absolute_url) {
+        if !unsupported.contains(&absolute_url) {
+            unsupported.push(absolute_url);
+        }
+        return Ok(false);
+    }
+    // Directory listings and other index pages aren't files
+    if !names_a_file(&absolute_url) {
+        return Ok(false);
+    }
+    if links.contains(&absolute_url) {
+        return Ok(false);
+    }
+    if name_clashes(&absolute_url, links) {
+        if !clashing.contains(&absolute_url) {
+            clashing.push(absolute_url);
+        }
+        return Ok(false);
+    }
+    links.push(absolute_url);
+    Ok(true)
+}
+
+/// Whether the last path segment of `href` is a data file name, such as
+/// `KHTX20250315_000412_V06` or `....tar.gz`.
+fn names_data_file(href: &str) -> bool {
+    let path = href.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    name.ends_with(".gz") || name.ends_with(".tar") || name.ends_with(".bz2") || name.contains("V06")
+}
+
+/// Scripts that may hold a page's data as JSON.
+const JSON_DATA_SELECTOR: &str = "script#__DATA__, script#__NEXT_DATA__, script[type='application/json']";
+
+/// Parses the JSON out of each data-island script on the page. Handles both
+/// pure JSON scripts and `window.__DATA__ = {...};` style assignments.
+fn json_data_islands(document: &Html) -> Vec<serde_json::Value> {
+    let Ok(selector) = Selector::parse("script") else {
+        return Vec::new();
+    };
+    let Ok(data_selector) = Selector::parse(JSON_DATA_SELECTOR) else {
+        return Vec::new();
+    };
+
+    document
+        .select(&selector)
+        .filter_map(|script| {
+            let text = script.text().collect::<String>();
+            if !data_selector.matches(&script) && !text.contains("__DATA__") {
+                return None;
+            }
+            let text = text.trim();
+            serde_json::from_str(text).ok().or_else(|| {
+                let start = text.find(['{', '['])?;
+                let end = text.rfind(['}', ']'])?;
+                serde_json::from_str(text.get(start..=end)?).ok()
+            })
+        })
+        .collect()
+}
+
+fn collect_json_strings<'a>(value: &'a serde_json::Value, strings: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::String(s) => strings.push(s),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_json_strings(item, strings);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values() {
+                collect_json_strings(field, strings);
             }
         }
+        _ => {}
+    }
+}
+
+/// Tells the user about links dropped for their scheme or name. Goes to
+/// stderr so `--dump-links` output stays just the links.
+fn report_skipped(extracted: &ExtractedLinks) {
+    for link in &extracted.unsupported {
+        eprintln!("Skipping {}: only http and https downloads are supported", link);
+    }
+    for link in &extracted.clashing {
+        eprintln!("Skipping {}: another link is already saved as {}", link, filename_from_url(link));
+    }
+}
+
+async fn fetch_download_links(radar_url: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let html = fetch_index(radar_url).await?;
+
+    println!("Fetching from URL: {}", radar_url);
+
+    let document = Html::parse_document(&html);
+    let extracted = extract_links(radar_url, &document)?;
+    report_skipped(&extracted);
+    let ExtractedLinks { links, selector, .. } = extracted;
+
+
+    if let Some(selector_str) = selector {
+        println!("Found {} links using selector: {}", links.len(), selector_str);
     }
 
     // Debug: print the first few characters of HTML to see structure
@@ -133,74 +302,511 @@ fn resolve_url(base_url: &str, link: &str) -> Result<String, Box<dyn std::error:
     Ok(resolved.to_string())
 }
 
+/// Whether `url` uses a scheme `download_file` can fetch. Index pages
+/// sometimes link files over other schemes, such as `ftp://`.
+fn is_supported_scheme(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Whether `url`'s path ends in a file name, rather than `/` as directory
+/// listings and query-only URLs such as `https://host/?id=1` do.
+fn names_a_file(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .is_some_and(|last| !last.is_empty()),
+        Err(_) => false,
+    }
+}
+
+/// The name a downloaded file is saved under: the last segment of the URL's
+/// path, plus its query string when there is one, so `download.jsp?id=1` and
+/// `download.jsp?id=2` stay apart as `download.jsp_id=1` and
+/// `download.jsp_id=2`. Characters that aren't safe in filenames become `_`,
+/// and fragments are dropped.
+fn filename_from_url(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return "unknown_file".to_string();
+    };
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("unknown_file");
+    match url.query() {
+        Some(query) if !query.is_empty() => {
+            let query: String = query
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || "._-=%".contains(c) { c } else { '_' })
+                .collect();
+            format!("{}_{}", name, query)
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Whether `url` would be saved under the same name as one of `links`, such
+/// as the same file offered by two mirrors.
+fn name_clashes(url: &str, links: &[String]) -> bool {
+    let name = filename_from_url(url);
+    links.iter().any(|link| filename_from_url(link) == name)
+}
+
+/// Refuses to overwrite anything at `path` that isn't a regular file.
+/// `File::create` would follow a symlink out of the output directory and
+/// block forever opening a FIFO.
+async fn ensure_writable_target(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) if !metadata.file_type().is_file() => {
+            let kind = if metadata.file_type().is_symlink() {
+                "a symlink"
+            } else if metadata.is_dir() {
+                "a directory"
+            } else {
+                "not a regular file"
+            };
+            Err(format!("refusing to write {}: target is {}", path.display(), kind).into())
+        }
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Settings shared by every download in a batch.
+#[derive(Clone)]
+struct DownloadOptions {
+    max_concurrent: usize,
+    /// How many download tasks may exist at once, running or waiting for a slot
+    task_buffer: usize,
+    /// Adjusts the number of download slots from measured throughput, for
+    /// `--concurrency auto`; replaces `max_concurrent` when set
+    tuner: Option<Arc<ConcurrencyTuner>>,
+    /// Abandon any file whose transfer hasn't finished this long after it
+    /// started; writing and decoding aren't cut off
+    file_deadline: Option<Duration>,
+    /// Flush each file and its directory to disk before counting it done
+    fsync: bool,
+    /// Limits how many files are written to disk at once, when set
+    write_slots: Option<Arc<Semaphore>>,
+    /// Waits out network outages instead of failing, when set
+    network: Option<Arc<NetworkGate>>,
+    /// Holds or fails downloads while the output volume is low on space
+    disk_guard: Option<Arc<DiskGuard>>,
+    /// Run on each file after it's written
+    decoder: Option<Arc<dyn Decoder>>,
+    /// Delete the raw file once `decoder` succeeds
+    discard_raw: bool,
+    /// Write a `<filename>.json` sidecar describing each download
+    per_file_metadata: bool,
+    /// Save zero-byte responses instead of failing them
+    allow_empty: bool,
+    /// The run's --tag, recorded in sidecars
+    tag: Option<String>,
+    /// Store files by content hash here instead of by name in the output directory
+    cas: Option<Arc<ContentStore>>,
+}
+
+impl Default for DownloadOptions {
+    /// One file at a time, with every optional behaviour off.
+    fn default() -> Self {
+        Self {
+            max_concurrent: 1,
+            task_buffer: 1,
+            tuner: None,
+            file_deadline: None,
+            fsync: false,
+            write_slots: None,
+            network: None,
+            disk_guard: None,
+            decoder: None,
+            discard_raw: false,
+            per_file_metadata: false,
+            allow_empty: false,
+            tag: None,
+            cas: None,
+        }
+    }
+}
+
+struct DownloadedFile {
+    url: String,
+    filename: String,
+    /// Host the file was fetched from
+    host: String,
+    bytes: u64,
+    /// Time spent transferring, from sending the request to the last byte
+    transfer_time: Duration,
+    downloaded_at: chrono::DateTime<chrono::Utc>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Hex SHA-256 of the body as received
+    sha256: String,
+}
+
+impl DownloadedFile {
+    fn metadata_json(&self, tag: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "tag": tag,
+            "url": self.url,
+            "filename": self.filename,
+            "bytes": self.bytes,
+            "sha256": self.sha256,
+            "downloaded_at": self.downloaded_at.to_rfc3339(),
+            "transfer_secs": self.transfer_time.as_secs_f64(),
+            "etag": self.etag,
+            "last_modified": self.last_modified,
+        })
+    }
+}
+
+/// Flushes a directory's entries to disk so newly created files survive a
+/// power loss. Directories can't be opened for syncing on Windows, where this
+/// does nothing.
+async fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir).await?.sync_all().await?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
 async fn download_file(
     url: &str,
     output_dir: &str,
-    progress: Arc<DownloadProgress>,
     client: &Client,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let response = client.get(url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
-        .await?;
-
-    let filename = Path::new(url)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("unknown_file");
-
+    options: &DownloadOptions,
+    deadline: Option<tokio::time::Instant>,
+) -> Result<DownloadedFile, Box<dyn std::error::Error>> {
+    let filename = &filename_from_url(url);
     let file_path = Path::new(output_dir).join(filename);
-    let mut file = File::create(&file_path).await?;
+    if options.cas.is_none() {
+        ensure_writable_target(&file_path).await?;
+    }
+
+    if let Some(guard) = &options.disk_guard {
+        guard.wait_for_space().await?;
+    }
+
+    let transfer_started = Instant::now();
+    let transfer = async {
+        let _connection = connections::acquire().await;
+        let response = client.get(url)
+            .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+            .send()
+            .await?;
+
+        let header = |name: HeaderName| {
+            response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let bytes = response.bytes().await?;
+        Ok::<_, reqwest::Error>((bytes, etag, last_modified))
+    };
+    // The deadline only cuts off the transfer. Once the body is in memory the
+    // write and decode always run to completion, so a file abandoned at the
+    // deadline never leaves a truncated file or a stray decoder behind.
+    let (bytes, etag, last_modified) = match (options.file_deadline, deadline) {
+        (Some(limit), Some(deadline)) => tokio::time::timeout_at(deadline, transfer)
+            .await
+            .map_err(|_| DeadlineExceeded(limit))??,
+        _ => transfer.await?,
+    };
+    let transfer_time = transfer_started.elapsed();
+
+    // An empty 200 is a server hiccup, not a radar volume
+    if bytes.is_empty() && !options.allow_empty {
+        return Err("server returned an empty file (pass --allow-empty to keep it)".into());
+    }
+    let sha256 = cas::sha256_hex(&bytes);
+
+    // The body stays buffered in memory while waiting for a write slot
+    let write_permit = match &options.write_slots {
+        Some(slots) => Some(slots.acquire().await?),
+        None => None,
+    };
+    if let Some(store) = &options.cas {
+        store.put(filename, url, &bytes, &sha256, options.fsync).await?;
+    } else {
+        let mut file = File::create(&file_path).await?;
+        file.write_all(&bytes).await?;
+
+        if options.fsync {
+            file.sync_all().await?;
+            sync_dir(Path::new(output_dir)).await?;
+        }
+    }
+    drop(write_permit);
+
+    let downloaded = DownloadedFile {
+        url: url.to_string(),
+        filename: filename.to_string(),
+        host: Url::parse(url)?.host_str().unwrap_or_default().to_string(),
+        bytes: bytes.len() as u64,
+        transfer_time,
+        downloaded_at: chrono::Utc::now(),
+        etag,
+        last_modified,
+        sha256,
+    };
+
+    if options.per_file_metadata {
+        let sidecar = Path::new(output_dir).join(format!("{}.json", filename));
+        ensure_writable_target(&sidecar).await?;
+        tokio::fs::write(&sidecar, serde_json::to_vec_pretty(&downloaded.metadata_json(options.tag.as_deref()))?).await?;
+    }
+
+    if let Some(decoder) = &options.decoder {
+        let decoder = Arc::clone(decoder);
+        let input = file_path.clone();
+        tokio::task::spawn_blocking(move || decoder.decode(&input)).await??;
+        if options.discard_raw {
+            tokio::fs::remove_file(&file_path).await?;
+        }
+    }
+
+    Ok(downloaded)
+}
+
+/// A transfer cut off by `--file-deadline`.
+#[derive(Debug)]
+struct DeadlineExceeded(Duration);
+
+impl std::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deadline of {}s exceeded", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+/// How a batch of downloads went.
+struct BatchOutcome {
+    downloaded: Vec<DownloadedFile>,
+    /// Failures caused by `--file-deadline` running out
+    deadline_exceeded: usize,
+}
+
+enum FileResult {
+    Downloaded(DownloadedFile),
+    Failed,
+    DeadlineExceeded,
+}
+
+/// Retries a download that failed to connect if `--wait-for-network` saw the
+/// network drop and come back.
+async fn download_file_waiting_for_network(
+    url: &str,
+    output_dir: &str,
+    client: &Client,
+    options: &DownloadOptions,
+    deadline: Option<tokio::time::Instant>,
+) -> Result<DownloadedFile, Box<dyn std::error::Error>> {
+    const MAX_NETWORK_RETRIES: usize = 3;
 
-    let bytes = response.bytes().await?;
-    file.write_all(&bytes).await?;
+    let Some(gate) = &options.network else {
+        return download_file(url, output_dir, client, options, deadline).await;
+    };
 
-    progress.increment(filename);
-    Ok(filename.to_string())
+    let mut attempt = 0;
+    loop {
+        // Box<dyn Error> isn't Send, so only the message is kept across the wait
+        let message = {
+            let result = download_file(url, output_dir, client, options, deadline).await;
+            match &result {
+                Err(e) if attempt < MAX_NETWORK_RETRIES && network::is_connection_error(e.as_ref()) => {
+                    e.to_string()
+                }
+                _ => return result,
+            }
+        };
+        match gate.on_connection_failure(url, Instant::now()).await {
+            AfterFailure::Retry => attempt += 1,
+            AfterFailure::GiveUp => return Err(message.into()),
+        }
+    }
 }
 
-async fn download_files(links: Vec<String>, output_dir: &str) -> Vec<String> {
-    let progress = Arc::new(DownloadProgress::new(links.len()));
-    let client = Client::builder()
+async fn download_files(
+    links: Vec<String>,
+    output_dir: &str,
+    options: DownloadOptions,
+    progress: Option<Arc<dyn Progress>>,
+) -> BatchOutcome {
+    let total = links.len();
+    if let Some(progress) = &progress {
+        progress.on_start(total);
+    }
+    let client = connections::client_builder()
         .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .timeout(std::time::Duration::from_secs(300)) // 5-minute timeout
+        .timeout(Duration::from_secs(300)) // 5-minute timeout
         .build()
         .expect("Failed to create HTTP client");
-    let max_concurrent = 50;
 
-    // Create semaphore using tokio::sync::Semaphore
-    let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent));
+    // Create semaphore using tokio::sync::Semaphore. A download holds its
+    // permit until the file is written, which bounds how many bodies can be
+    // buffered in memory waiting on --write-concurrency.
+    let semaphore = match &options.tuner {
+        Some(tuner) => tuner.slots(),
+        None => Arc::new(Semaphore::new(options.max_concurrent)),
+    };
+    let options = Arc::new(options);
 
-    let mut tasks = Vec::new();
+    // Tasks are spawned only as the buffer has room, so memory and scheduler
+    // load stay bounded however many links there are
+    let mut results = stream::iter(links)
+        .map(|link| {
+            let progress_clone = progress.clone();
+            let client_clone = client.clone();
+            let output_dir_clone = output_dir.to_string();
+            let semaphore_clone = Arc::clone(&semaphore);
+            let options_clone = Arc::clone(&options);
 
-    for link in links {
-        let progress_clone = Arc::clone(&progress);
-        let client_clone = client.clone();
-        let output_dir_clone = output_dir.to_string();
-        let semaphore_clone = Arc::clone(&semaphore);
-
-        let task = tokio::spawn(async move {
-            let _permit = semaphore_clone.acquire().await.unwrap();
-            match download_file(&link, &output_dir_clone, progress_clone, &client_clone).await {
-                Ok(filename) => Some(filename),
-                Err(e) => {
-                    eprintln!("\nError downloading {}: {}", link, e);
-                    None
+            tokio::spawn(async move {
+                let _permit = semaphore_clone.acquire().await.unwrap();
+                let deadline = options_clone.file_deadline.map(|limit| tokio::time::Instant::now() + limit);
+                let result =
+                    download_file_waiting_for_network(&link, &output_dir_clone, &client_clone, &options_clone, deadline)
+                        .await;
+                match result {
+                    Ok(downloaded) => {
+                        if let Some(tuner) = &options_clone.tuner {
+                            tuner.record(downloaded.bytes);
+                        }
+                        if let Some(progress) = &progress_clone {
+                            progress.on_file_done(&downloaded.filename, downloaded.bytes);
+                        }
+                        FileResult::Downloaded(downloaded)
+                    }
+                    Err(e) => {
+                        if let Some(progress) = &progress_clone {
+                            progress.on_error(&link, &e.to_string());
+                        }
+                        if e.is::<DeadlineExceeded>() {
+                            FileResult::DeadlineExceeded
+                        } else {
+                            FileResult::Failed
+                        }
+                    }
                 }
-            }
-        });
+            })
+        })
+        .buffer_unordered(options.task_buffer.max(1));
+
+    let mut outcome = BatchOutcome {
+        downloaded: Vec::new(),
+        deadline_exceeded: 0,
+    };
+    while let Some(result) = results.next().await {
+        match result {
+            Ok(FileResult::Downloaded(downloaded)) => outcome.downloaded.push(downloaded),
+            Ok(FileResult::DeadlineExceeded) => outcome.deadline_exceeded += 1,
+            Ok(FileResult::Failed) | Err(_) => {}
+        }
+    }
+
+    if let Some(progress) = &progress {
+        progress.on_finish(outcome.downloaded.len(), total - outcome.downloaded.len());
+    }
+    outcome
+}
+
+/// Drops every link up to and including the first one matching `is_last_done`,
+/// or returns `None` if none match.
+fn resume_after(mut links: Vec<String>, is_last_done: impl Fn(&str) -> bool) -> Option<Vec<String>> {
+    let position = links.iter().position(|link| is_last_done(link))?;
+    eprintln!("Resuming after {} ({} links skipped)", links[position], position + 1);
+    Some(links.split_off(position + 1))
+}
+
+/// Applies `--max-age`: a date more than `max_age` before today is an error,
+/// or just a warning under `--on-stale warn`.
+fn check_max_age(date: NaiveDate, max_age: Duration, policy: cli::StalePolicy) -> Result<(), Box<dyn std::error::Error>> {
+    let today = chrono::Utc::now().date_naive();
+    let age_days = (today - date).num_days();
+    let max_days = (max_age.as_secs() / (24 * 60 * 60)) as i64;
+    if age_days <= max_days {
+        return Ok(());
+    }
+    let message = format!("{} is {} days old, older than --max-age allows ({} days)", date, age_days, max_days);
+    match policy {
+        cli::StalePolicy::Warn => {
+            eprintln!("Warning: {}", message);
+            Ok(())
+        }
+        cli::StalePolicy::Error => Err(format!("{}; pass --on-stale warn to fetch it anyway", message).into()),
+    }
+}
+
+/// Reorders `links` to follow the filenames listed in `path`, one per line.
+/// Links not listed keep their relative order after the listed ones, or are
+/// dropped if `drop_unlisted` is set.
+fn order_by_file(links: Vec<String>, path: &str, drop_unlisted: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let total = links.len();
+    let names = contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let (ordered, unknown) = order_by_names(links, names, drop_unlisted);
+
+    if !unknown.is_empty() {
+        eprintln!(
+            "Warning: {} names in {} match no discovered link: {}",
+            unknown.len(),
+            path,
+            unknown.join(", ")
+        );
+    }
+    if drop_unlisted && ordered.len() < total {
+        eprintln!("Skipping {} links not listed in {}", total - ordered.len(), path);
+    }
+    Ok(ordered)
+}
 
-        tasks.push(task);
+/// Puts the links saved under each of `names` first, in that order, followed
+/// by the rest unless `drop_unlisted`. Also returns the names no link has.
+fn order_by_names<'a>(
+    links: Vec<String>,
+    names: impl IntoIterator<Item = &'a str>,
+    drop_unlisted: bool,
+) -> (Vec<String>, Vec<&'a str>) {
+    // Index by filename once, so huge link sets aren't rescanned per name
+    let mut by_name: HashMap<String, VecDeque<usize>> = HashMap::new();
+    for (index, link) in links.iter().enumerate() {
+        by_name.entry(filename_from_url(link)).or_default().push_back(index);
     }
 
-    let results = join_all(tasks).await;
-    let downloaded_files: Vec<String> = results
-        .into_iter()
-        .filter_map(|result| result.ok().flatten())
-        .collect();
+    let mut remaining: Vec<Option<String>> = links.into_iter().map(Some).collect();
+    let mut ordered = Vec::with_capacity(remaining.len());
+    let mut unknown = Vec::new();
+    for name in names {
+        match by_name.get_mut(name).and_then(VecDeque::pop_front) {
+            Some(index) => ordered.extend(remaining[index].take()),
+            None => unknown.push(name),
+        }
+    }
+    if !drop_unlisted {
+        ordered.extend(remaining.into_iter().flatten());
+    }
+    (ordered, unknown)
+}
 
-    println!(); // New line after progress
-    downloaded_files
+/// Groups `links` by host, keeping hosts in order of their first link and
+/// links in their original order within each host.
+fn batch_by_host(links: Vec<String>) -> Vec<String> {
+    let mut hosts: Vec<(String, Vec<String>)> = Vec::new();
+    for link in links {
+        let host = Url::parse(&link)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        match hosts.iter_mut().find(|(existing, _)| *existing == host) {
+            Some((_, batch)) => batch.push(link),
+            None => hosts.push((host, vec![link])),
+        }
+    }
+    hosts.into_iter().flat_map(|(_, batch)| batch).collect()
 }
 
 fn prompt_input(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
@@ -209,41 +815,433 @@ fn prompt_input(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
 
     let stdin = io::stdin();
     let mut line = String::new();
-    stdin.read_line(&mut line)?;
+    if stdin.read_line(&mut line)? == 0 {
+        println!();
+        return Err(format!("no input for '{}': stdin is closed", prompt.trim().trim_end_matches(':')).into());
+    }
 
-    Ok(line.trim().to_string())
+    let value = line.trim();
+    if value.is_empty() {
+        return Err(format!("no input for '{}': a value is required", prompt.trim().trim_end_matches(':')).into());
+    }
+    Ok(value.to_string())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let radar = prompt_input("Enter radar site (KHTX): ")?;
-    let radar = radar.to_uppercase();
-    let month = prompt_input("Enter month (03): ")?;
-    let day = prompt_input("Enter day (15): ")?;
-    let year = prompt_input("Enter year (2025): ")?;
+    let args = cli::Args::parse();
+    if let Some(max) = args.max_connections_total {
+        connections::set_max_connections(max as usize);
+    }
+    if let Some(version) = args.strict_tls_version {
+        connections::set_min_tls_version(version.into());
+        // Surface an unusable TLS setup now rather than on the first request
+        connections::client_builder().build()?;
+    }
 
-    let url = format!(
-        "https://www.ncdc.noaa.gov/nexradinv/bdp-download.jsp?id={}&yyyy={}&mm={}&dd={}&product=AAL2",
-        radar, year, month, day
-    );
+    if let Some(accept) = &args.accept {
+        let _ = INDEX_ACCEPT.set(accept.clone());
+    }
 
-    let output_dir = format!("{}_{}_{}_{}", radar, year, month, day);
+    if let Some(cli::Command::SelfTest) = args.command {
+        return selftest::self_test().await;
+    }
 
-    fs::create_dir_all(&output_dir)?;
+    let robots = args.respect_robots.then(RobotsCache::new);
+
+    let (links, output_dir, index, target, date) = match &args.links_file {
+        Some(path) => {
+            let output_dir = args.output_dir.clone().or_else(|| args.cas.clone()).unwrap_or_default();
+            let source = if path == "-" { "stdin".to_string() } else { path.clone() };
+            if args.explain {
+                explain::explain(&args, &explain::Source::LinksFile(&source), &output_dir);
+                return Ok(());
+            }
+            if args.preflight {
+                let target = preflight::Target::LinksFile(path);
+                return preflight::preflight(&target, &output_dir, args.min_free_space).await;
+            }
+            let links = links_file::read_links(path)?;
+            (links, output_dir, None, source, None)
+        }
+        None => {
+            let radar = prompt_input("Enter radar site (KHTX): ")?;
+            let radar = radar.to_uppercase();
+
+            if let Some(range) = args.probe_date_availability {
+                probe::probe_date_availability(&args.base_url, &radar, range, args.output_format).await;
+                return Ok(());
+            }
+
+            let month = prompt_input("Enter month (03): ")?;
+            let day = prompt_input("Enter day (15): ")?;
+            let year = prompt_input("Enter year (2025): ")?;
+
+            let date = match (year.parse(), month.parse(), day.parse()) {
+                (Ok(y), Ok(m), Ok(d)) => NaiveDate::from_ymd_opt(y, m, d),
+                _ => None,
+            };
+            if let (Some(max_age), Some(date)) = (args.max_age, date) {
+                check_max_age(date, max_age, args.on_stale)?;
+            }
+
+            let url = index_url(&args.base_url, &radar, &year, &month, &day);
+
+            let output_dir = args
+                .output_dir
+                .clone()
+                .or_else(|| args.cas.clone())
+                .unwrap_or_else(|| format!("{}_{}_{}_{}", radar, year, month, day));
+
+            if args.explain {
+                let date = format!("{}-{}-{}", year, month, day);
+                let source = explain::Source::Index { site: &radar, date: &date, url: &url };
+                explain::explain(&args, &source, &output_dir);
+                return Ok(());
+            }
+
+            if args.preflight {
+                let target = preflight::Target::Index { site: &radar, year: &year, month: &month, day: &day, url: &url };
+                return preflight::preflight(&target, &output_dir, args.min_free_space).await;
+            }
+
+            if let Some(robots) = &robots
+                && !robots.allows(&url).await {
+                return Err(format!("robots.txt disallows fetching {}", url).into());
+            }
+
+            let links = if args.dump_links {
+                // Keep stdout to just the links
+                let html = fetch_index(&url).await?;
+                let extracted = extract_links(&url, &Html::parse_document(&html))?;
+                report_skipped(&extracted);
+                extracted.links
+            } else {
+                println!("Fetching download links...");
+                fetch_download_links(&url).await?
+            };
+            let target = format!("{} {}-{}-{}", radar, year, month, day);
+            (links, output_dir, Some(url), target, date)
+        }
+    };
+
+    let links = match &robots {
+        Some(robots) => {
+            let mut allowed = Vec::with_capacity(links.len());
+            for link in links {
+                if robots.allows(&link).await {
+                    allowed.push(link);
+                } else {
+                    eprintln!("Skipping {}: disallowed by robots.txt", link);
+                }
+            }
+            allowed
+        }
+        None => links,
+    };
+
+    let links = if let Some(url) = &args.resume_from_url {
+        resume_after(links, |link| link == url)
+            .ok_or_else(|| format!("--resume-from-url {} is not in the link list", url))?
+    } else if let Some(name) = &args.resume_from_file {
+        resume_after(links, |link| filename_from_url(link) == *name)
+            .ok_or_else(|| format!("--resume-from-file {} is not in the link list", name))?
+    } else {
+        links
+    };
 
-    println!("Fetching download links...");
-    let links = fetch_download_links(&url).await?;
+    let links = if args.batch_by_host { batch_by_host(links) } else { links };
+    let links = match &args.download_order_file {
+        Some(path) => order_by_file(links, path, args.drop_unlisted)?,
+        None => links,
+    };
+
+    if args.dump_links {
+        match args.output_format {
+            cli::OutputFormat::Human => {
+                for link in &links {
+                    println!("{}", link);
+                }
+            }
+            cli::OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&links)?),
+        }
+        return Ok(());
+    }
 
     if links.is_empty() {
         println!("No download links found. Please check your input parameters.");
         return Ok(());
     }
 
+    fs::create_dir_all(&output_dir)?;
+
+    let concurrency = args.concurrency.resolve();
+    let tuner = match args.concurrency {
+        cli::Concurrency::Auto if !args.touch_only => {
+            println!(
+                "Auto concurrency: starting at {}, adjusting from measured throughput (up to {})",
+                concurrency,
+                autotune::MAX_AUTO_CONCURRENCY
+            );
+            Some(Arc::new(ConcurrencyTuner::new(concurrency, autotune::MAX_AUTO_CONCURRENCY)))
+        }
+        cli::Concurrency::Auto => {
+            println!("Auto-selected concurrency: {}", concurrency);
+            None
+        }
+        cli::Concurrency::Fixed(_) => None,
+    };
+
+    if args.touch_only {
+        return touch::touch_existing(&links, Path::new(&output_dir), concurrency, args.probe_method).await;
+    }
+
     println!("Found {} files to download", links.len());
-    let downloaded_files = download_files(links, &output_dir).await;
+    let started = Instant::now();
+    let total_links = links.len();
+    let progress: Arc<dyn Progress> = match args.progress_refresh_rate {
+        Some(rate) => Arc::new(ConsoleProgress::with_refresh_rate(rate)),
+        None => Arc::new(ConsoleProgress::new()),
+    };
+    let decoder = match &args.decode_command {
+        Some(command) => {
+            let decoder = CommandDecoder::new(command, &args.decode_extension)?;
+            Some(Arc::new(decoder) as Arc<dyn Decoder>)
+        }
+        None => None,
+    };
+    let options = DownloadOptions {
+        max_concurrent: concurrency,
+        task_buffer: args.task_buffer.map_or_else(
+            || if tuner.is_some() { autotune::MAX_AUTO_CONCURRENCY } else { concurrency },
+            |n| n as usize,
+        ),
+        tuner: tuner.clone(),
+        file_deadline: args.file_deadline,
+        fsync: args.fsync,
+        write_slots: args.write_concurrency.map(|n| Arc::new(Semaphore::new(n as usize))),
+        network: args.wait_for_network.map(|max_wait| Arc::new(NetworkGate::new(max_wait))),
+        disk_guard: args.min_free_space.map(|min_free| {
+            Arc::new(DiskGuard::new(PathBuf::from(&output_dir), min_free, args.on_low_space))
+        }),
+        decoder,
+        discard_raw: args.discard_raw,
+        per_file_metadata: args.per_file_metadata,
+        allow_empty: args.allow_empty,
+        tag: args.tag.clone(),
+        cas: args.cas.as_ref().map(|dir| Arc::new(ContentStore::new(PathBuf::from(dir)))),
+    };
+    let outcome = download_files(links, &output_dir, options, Some(progress)).await;
+    if let Some(tuner) = &tuner {
+        println!("Auto concurrency settled at {}", tuner.current());
+    }
+
+    if args.with_reports {
+        match date {
+            Some(date) => match reports::fetch_storm_reports(Path::new(&output_dir), date).await {
+                Ok(path) => println!("Storm reports saved to {}", path.display()),
+                Err(e) => eprintln!("Could not fetch storm reports: {}", e),
+            },
+            None => eprintln!("Could not fetch storm reports: the date entered is not a valid calendar date"),
+        }
+    }
+
+    if let (true, Some(url)) = (args.reconcile, &index) {
+        let decoded_extension = args
+            .decode_command
+            .as_ref()
+            .map(|_| args.decode_extension.trim_start_matches('.'));
+        reconcile::reconcile(url, Path::new(&output_dir), decoded_extension, args.output_format).await?;
+    }
+
+    if args.normalize_output {
+        let filenames = outcome.downloaded.iter().map(|file| file.filename.as_str());
+        let renamed = normalize::normalize_extensions(Path::new(&output_dir), filenames)?;
+        println!("Normalized extensions: {} files renamed", renamed);
+    }
+
+    let archive = match args.archive_on_complete {
+        Some(format) => {
+            let dir = PathBuf::from(&output_dir);
+            let remove_loose = args.remove_after_archive;
+            let path = tokio::task::spawn_blocking(move || {
+                archive::archive_directory(&dir, format, remove_loose)
+            })
+            .await??;
+            Some(path)
+        }
+        None => None,
+    };
 
-    println!("Total files downloaded: {}", downloaded_files.len());
-    println!("Files saved in: {}", output_dir);
+    let summary = summary::RunSummary {
+        target,
+        tag: args.tag.clone(),
+        downloaded: outcome.downloaded.len(),
+        failed: total_links - outcome.downloaded.len(),
+        deadline_exceeded: outcome.deadline_exceeded,
+        bytes: outcome.downloaded.iter().map(|file| file.bytes).sum(),
+        elapsed: started.elapsed(),
+        output_dir,
+        archive,
+        hosts: if args.report_bandwidth_per_host {
+            summary::usage_by_host(
+                outcome.downloaded.iter().map(|file| (file.host.as_str(), file.bytes, file.transfer_time)),
+            )
+        } else {
+            Vec::new()
+        },
+    };
+    summary.print(args.summary_format);
+    if let Some(path) = &args.metrics_file {
+        metrics::write_metrics_file(path, &summary)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("radar-fetcher-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn refuses_symlink_and_directory_targets() {
+        let dir = scratch_dir("targets");
+        let outside = dir.join("outside");
+        fs::write(&outside, b"keep").unwrap();
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+        let subdir = dir.join("subdir");
+        fs::create_dir(&subdir).unwrap();
+
+        assert!(ensure_writable_target(&link).await.is_err());
+        assert!(ensure_writable_target(&subdir).await.is_err());
+        assert!(ensure_writable_target(&outside).await.is_ok());
+        assert!(ensure_writable_target(&dir.join("new")).await.is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    const INDEX: &str = "https://www.ncdc.noaa.gov/nexradinv/bdp-download.jsp";
+
+    #[test]
+    fn ftp_links_are_unsupported() {
+        let html = Html::parse_document(
+            r#"<a href="ftp://ftp.example.com/KHTX20250315_000412_V06.gz">ftp</a>
+               <a href="https://data.example.com/KHTX20250315_001003_V06.gz">https</a>"#,
+        );
+        let extracted = extract_links(INDEX, &html).unwrap();
+        assert_eq!(extracted.links, ["https://data.example.com/KHTX20250315_001003_V06.gz"]);
+        assert_eq!(extracted.unsupported, ["ftp://ftp.example.com/KHTX20250315_000412_V06.gz"]);
+    }
+
+    #[test]
+    fn directory_and_query_only_urls_are_not_files() {
+        assert!(!names_a_file("https://data.example.com/nexrad/"));
+        assert!(!names_a_file("https://data.example.com/"));
+        assert!(!names_a_file("https://data.example.com/?id=KHTX"));
+        assert!(names_a_file("https://data.example.com/nexrad/KHTX20250315_000412_V06"));
+
+        let html = Html::parse_document(
+            r#"<a href="/archive/V06/">listing</a><a href="/archive/V06/KHTX20250315_000412_V06">file</a>"#,
+        );
+        let extracted = extract_links(INDEX, &html).unwrap();
+        assert_eq!(extracted.links, ["https://www.ncdc.noaa.gov/archive/V06/KHTX20250315_000412_V06"]);
+    }
+
+    #[test]
+    fn filenames_exclude_query_and_fragment() {
+        assert_eq!(filename_from_url("https://h/download.jsp?id=1"), "download.jsp_id=1");
+        assert_eq!(filename_from_url("https://h/get?file=a/b.gz&x=1"), "get_file=a_b.gz_x=1");
+        assert_eq!(filename_from_url("https://h/data/KHTX20250315_000412_V06.gz#top"), "KHTX20250315_000412_V06.gz");
+        assert_eq!(filename_from_url("https://h/dir/"), "unknown_file");
+    }
+
+    #[test]
+    fn query_distinguished_links_get_distinct_names() {
+        let html = Html::parse_document(
+            r#"<a href="https://www.ncdc.noaa.gov/nexradinv/download.jsp?id=1">first</a>
+               <a href="https://www.ncdc.noaa.gov/nexradinv/download.jsp?id=2">second</a>
+               <a href="https://mirror.example.com/nexradinv/download.jsp?id=1">mirror</a>"#,
+        );
+        let extracted = extract_links(INDEX, &html).unwrap();
+        assert_eq!(
+            extracted.links,
+            [
+                "https://www.ncdc.noaa.gov/nexradinv/download.jsp?id=1",
+                "https://www.ncdc.noaa.gov/nexradinv/download.jsp?id=2",
+            ]
+        );
+        let names: Vec<String> = extracted.links.iter().map(|link| filename_from_url(link)).collect();
+        assert_eq!(names, ["download.jsp_id=1", "download.jsp_id=2"]);
+        assert_eq!(extracted.clashing, ["https://mirror.example.com/nexradinv/download.jsp?id=1"]);
+    }
+
+    #[test]
+    fn order_file_puts_listed_names_first() {
+        let links = || vec!["https://h/a".to_string(), "https://h/b".to_string(), "https://h/c".to_string()];
+
+        let (ordered, unknown) = order_by_names(links(), ["c", "zz", "a"], false);
+        assert_eq!(ordered, ["https://h/c", "https://h/a", "https://h/b"]);
+        assert_eq!(unknown, ["zz"]);
+
+        let (ordered, _) = order_by_names(links(), ["b"], true);
+        assert_eq!(ordered, ["https://h/b"]);
+    }
+
+    /// Serves `200 OK` with an empty body to every request on a loopback port.
+    async fn empty_body_server() -> String {
+        let (addr, _) = selftest::serve_loopback(|_| selftest::Reply {
+            status: "200 OK",
+            content_type: "application/octet-stream",
+            body: Vec::new(),
+        })
+        .await
+        .unwrap();
+        format!("http://{}/KTST20250315_000412_V06", addr)
+    }
+
+    #[tokio::test]
+    async fn empty_responses_fail_unless_allowed() {
+        let url = empty_body_server().await;
+        let dir = scratch_dir("empty");
+        let output_dir = dir.to_str().unwrap();
+        let client = Client::new();
+
+        assert!(download_file(&url, output_dir, &client, &DownloadOptions::default(), None).await.is_err());
+        assert!(!dir.join("KTST20250315_000412_V06").exists());
+
+        let downloaded = download_file(&url, output_dir, &client, &DownloadOptions { allow_empty: true, ..Default::default() }, None).await.unwrap();
+        assert_eq!(downloaded.bytes, 0);
+        assert_eq!(fs::metadata(dir.join("KTST20250315_000412_V06")).unwrap().len(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_listing_yields_only_data_files() {
+        let html = Html::parse_document(
+            r#"<html><body><script id="__DATA__">
+                {"product":"AAL2","page":"/nexradinv/download.jsp","files":["KHTX20250315_000412_V06.gz","KHTX20250315_001003_V06"]}
+            </script></body></html>"#,
+        );
+        let extracted = extract_links(INDEX, &html).unwrap();
+        assert_eq!(
+            extracted.links,
+            [
+                "https://www.ncdc.noaa.gov/nexradinv/KHTX20250315_000412_V06.gz",
+                "https://www.ncdc.noaa.gov/nexradinv/KHTX20250315_001003_V06",
+            ]
+        );
+        assert_eq!(extracted.selector, Some(JSON_DATA_SELECTOR));
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::summary::RunSummary;
+
+/// Writes the run's totals to `path` in the Prometheus/OpenMetrics text
+/// format, for node_exporter's textfile collector. The file is written under
+/// a temporary name and renamed, so a scrape never sees a partial snapshot.
+pub fn write_metrics_file(path: &Path, summary: &RunSummary) -> io::Result<()> {
+    let mut labels = format!("target=\"{}\"", escape_label(&summary.target));
+    if let Some(tag) = &summary.tag {
+        labels.push_str(&format!(",tag=\"{}\"", escape_label(tag)));
+    }
+    let mut text = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = writeln!(text, "# HELP radar_fetcher_{} {}", name, help);
+        let _ = writeln!(text, "# TYPE radar_fetcher_{} {}", name, kind);
+        let _ = writeln!(text, "radar_fetcher_{}{{{}}} {}", name, labels, value);
+    };
+    metric("files_downloaded", "gauge", "Files downloaded by the last run.", summary.downloaded.to_string());
+    metric("files_failed", "gauge", "Files that failed in the last run.", summary.failed.to_string());
+    metric(
+        "files_deadline_exceeded",
+        "gauge",
+        "Files abandoned at --file-deadline in the last run.",
+        summary.deadline_exceeded.to_string(),
+    );
+    metric("bytes_downloaded", "gauge", "Bytes downloaded by the last run.", summary.bytes.to_string());
+    metric(
+        "run_duration_seconds",
+        "gauge",
+        "Wall-clock duration of the last run.",
+        format!("{:.3}", summary.elapsed.as_secs_f64()),
+    );
+    metric(
+        "last_run_timestamp_seconds",
+        "gauge",
+        "Unix time the last run finished.",
+        chrono::Utc::now().timestamp().to_string(),
+    );
+
+    if !summary.hosts.is_empty() {
+        let _ = writeln!(text, "# HELP radar_fetcher_host_bytes_downloaded Bytes downloaded from each host by the last run.");
+        let _ = writeln!(text, "# TYPE radar_fetcher_host_bytes_downloaded gauge");
+        for usage in &summary.hosts {
+            let _ = writeln!(
+                text,
+                "radar_fetcher_host_bytes_downloaded{{{},host=\"{}\"}} {}",
+                labels,
+                escape_label(&usage.host),
+                usage.bytes
+            );
+        }
+    }
+    text.push_str("# EOF\n");
+
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(".tmp");
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path)
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
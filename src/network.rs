@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use url::Url;
+
+/// How often connectivity is re-checked while waiting for the network.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a single connectivity probe may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a download should do after a connection failure.
+pub enum AfterFailure {
+    /// The network was down and has come back; try again.
+    Retry,
+    /// Treat it as an ordinary failure.
+    GiveUp,
+}
+
+#[derive(Default)]
+struct GateState {
+    /// When the most recent outage was seen to end
+    last_recovery: Option<Instant>,
+    /// Set once an outage outlasted the maximum wait
+    abandoned: bool,
+}
+
+/// Pauses downloads that fail to connect while the network is unreachable,
+/// so a dropped connection doesn't turn every remaining file into a failure.
+///
+/// Failing downloads queue on a single lock: the first one probes the host
+/// until it is reachable again, and the rest see that the outage has already
+/// ended and retry straight away.
+pub struct NetworkGate {
+    max_wait: Duration,
+    state: Mutex<GateState>,
+}
+
+impl NetworkGate {
+    pub fn new(max_wait: Duration) -> Self {
+        Self {
+            max_wait,
+            state: Mutex::new(GateState::default()),
+        }
+    }
+
+    /// Called when connecting to `url` failed at `failed_at`.
+    pub async fn on_connection_failure(&self, url: &str, failed_at: Instant) -> AfterFailure {
+        let mut state = self.state.lock().await;
+        if state.abandoned {
+            return AfterFailure::GiveUp;
+        }
+        if state.last_recovery.is_some_and(|recovered| failed_at <= recovered) {
+            return AfterFailure::Retry;
+        }
+        if is_reachable(url).await {
+            return AfterFailure::GiveUp;
+        }
+
+        eprintln!(
+            "\nNetwork appears to be down; pausing downloads for up to {}s",
+            self.max_wait.as_secs()
+        );
+        let started = Instant::now();
+        while started.elapsed() < self.max_wait {
+            tokio::time::sleep(PROBE_INTERVAL).await;
+            if is_reachable(url).await {
+                eprintln!("Network is back after {}s; resuming", started.elapsed().as_secs());
+                state.last_recovery = Some(Instant::now());
+                return AfterFailure::Retry;
+            }
+        }
+
+        eprintln!("Network still down after {}s; giving up on waiting", self.max_wait.as_secs());
+        state.abandoned = true;
+        AfterFailure::GiveUp
+    }
+}
+
+/// Whether a TCP connection can be opened to `url`'s host.
+async fn is_reachable(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    // Probes count against --max-connections-total like any other request
+    let _connection = crate::connections::acquire().await;
+    matches!(
+        tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect((host, port))).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Whether `error` came from failing to reach the server at all.
+pub fn is_connection_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect())
+}
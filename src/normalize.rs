@@ -0,0 +1,152 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// File types recognized from their leading bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentType {
+    Gzip,
+    Bzip2,
+    Tar,
+}
+
+impl ContentType {
+    fn extension(self) -> &'static str {
+        match self {
+            ContentType::Gzip => "gz",
+            ContentType::Bzip2 => "bz2",
+            ContentType::Tar => "tar",
+        }
+    }
+
+    fn is_compression(self) -> bool {
+        matches!(self, ContentType::Gzip | ContentType::Bzip2)
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "gz" => Some(ContentType::Gzip),
+            "bz2" => Some(ContentType::Bzip2),
+            "tar" => Some(ContentType::Tar),
+            _ => None,
+        }
+    }
+}
+
+fn detect(path: &Path) -> io::Result<Option<ContentType>> {
+    let mut header = [0u8; 262];
+    let mut file = File::open(path)?;
+    let mut len = 0;
+    while len < header.len() {
+        match file.read(&mut header[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    let header = &header[..len];
+
+    Ok(if header.starts_with(&[0x1f, 0x8b]) {
+        Some(ContentType::Gzip)
+    } else if header.starts_with(b"BZh") {
+        Some(ContentType::Bzip2)
+    } else if header.get(257..262) == Some(b"ustar") {
+        Some(ContentType::Tar)
+    } else {
+        None
+    })
+}
+
+/// The name `filename` should have given its content, or `None` if it's
+/// already right or the content isn't recognized.
+fn normalized_name(filename: &str, content: ContentType) -> Option<String> {
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) => (stem, ContentType::from_extension(extension)),
+        None => (filename, None),
+    };
+    match extension {
+        Some(current) if current == content => None,
+        // A compressed tarball named `.tar` just needs the compression suffix
+        Some(ContentType::Tar) if content.is_compression() => {
+            Some(format!("{}.{}", filename, content.extension()))
+        }
+        // An uncompressed tarball named `.tar.gz` just loses the compression suffix
+        Some(current) if current.is_compression() && content == ContentType::Tar && stem.ends_with(".tar") => {
+            Some(stem.to_string())
+        }
+        Some(_) => Some(format!("{}.{}", stem, content.extension())),
+        None => Some(format!("{}.{}", filename, content.extension())),
+    }
+}
+
+/// Appends `-1`, `-2`, ... before the extension until the name is unused.
+fn free_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if fs::symlink_metadata(&candidate).is_err() {
+        return candidate;
+    }
+    let (stem, extension) = name.split_once('.').unwrap_or((name, ""));
+    (1..)
+        .map(|n| {
+            if extension.is_empty() {
+                dir.join(format!("{}-{}", stem, n))
+            } else {
+                dir.join(format!("{}-{}.{}", stem, n, extension))
+            }
+        })
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .unwrap()
+}
+
+/// Renames each of `filenames` in `dir` so its extension matches its content,
+/// never overwriting an existing file. Returns the number renamed.
+pub fn normalize_extensions<'a>(
+    dir: &Path,
+    filenames: impl IntoIterator<Item = &'a str>,
+) -> io::Result<usize> {
+    let mut renamed = 0;
+    for filename in filenames {
+        let path = dir.join(filename);
+        let Some(content) = detect(&path)? else {
+            continue;
+        };
+        let Some(new_name) = normalized_name(filename, content) else {
+            continue;
+        };
+        let new_path = free_path(dir, &new_name);
+        fs::rename(&path, &new_path)?;
+        println!("Renamed {} -> {}", filename, new_path.display());
+        renamed += 1;
+    }
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correct_names_are_left_alone() {
+        assert_eq!(normalized_name("a.gz", ContentType::Gzip), None);
+        assert_eq!(normalized_name("a.tar", ContentType::Tar), None);
+        assert_eq!(normalized_name("a.tar.bz2", ContentType::Bzip2), None);
+    }
+
+    #[test]
+    fn missing_extensions_are_appended() {
+        assert_eq!(normalized_name("KHTX20250315_000412_V06", ContentType::Gzip).as_deref(), Some("KHTX20250315_000412_V06.gz"));
+        assert_eq!(normalized_name("a.tar", ContentType::Gzip).as_deref(), Some("a.tar.gz"));
+    }
+
+    #[test]
+    fn wrong_extensions_are_replaced() {
+        assert_eq!(normalized_name("a.gz", ContentType::Bzip2).as_deref(), Some("a.bz2"));
+        assert_eq!(normalized_name("a.tar.bz2", ContentType::Gzip).as_deref(), Some("a.tar.gz"));
+    }
+
+    #[test]
+    fn uncompressed_tarball_drops_compression_suffix() {
+        assert_eq!(normalized_name("a.tar.gz", ContentType::Tar).as_deref(), Some("a.tar"));
+        assert_eq!(normalized_name("a.tar.bz2", ContentType::Tar).as_deref(), Some("a.tar"));
+        assert_eq!(normalized_name("a.gz", ContentType::Tar).as_deref(), Some("a.tar"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use scraper::Html;
+
+use crate::summary::format_bytes;
+use crate::{extract_links, fetch_index, links_file};
+
+/// What a run would download from.
+pub enum Target<'a> {
+    Index { site: &'a str, year: &'a str, month: &'a str, day: &'a str, url: &'a str },
+    LinksFile(&'a str),
+}
+
+struct Check {
+    name: &'static str,
+    result: Result<String, String>,
+}
+
+/// Runs every check a download run depends on, without downloading, and
+/// prints one line per check. Fails if any check does.
+pub async fn preflight(target: &Target<'_>, output_dir: &str, min_free: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut checks = Vec::new();
+
+    match target {
+        Target::Index { site, year, month, day, url } => {
+            checks.push(Check { name: "site", result: check_site(site) });
+            checks.push(Check { name: "date", result: check_date(year, month, day) });
+            checks.push(Check { name: "index", result: check_index(url).await });
+        }
+        Target::LinksFile(path) => {
+            checks.push(Check { name: "links file", result: check_links_file(path) });
+        }
+    }
+
+    let writable_dir = nearest_existing_dir(Path::new(output_dir));
+    checks.push(Check { name: "output directory", result: check_writable(output_dir, writable_dir) });
+    checks.push(Check { name: "disk space", result: check_space(writable_dir, min_free) });
+
+    println!("Preflight checks:");
+    for check in &checks {
+        match &check.result {
+            Ok(detail) => println!("  ok    {}: {}", check.name, detail),
+            Err(problem) => println!("  FAIL  {}: {}", check.name, problem),
+        }
+    }
+
+    let failed = checks.iter().filter(|check| check.result.is_err()).count();
+    if failed > 0 {
+        return Err(format!("{} of {} preflight checks failed", failed, checks.len()).into());
+    }
+    println!("All {} checks passed", checks.len());
+    Ok(())
+}
+
+fn check_site(site: &str) -> Result<String, String> {
+    let valid = site.len() == 4
+        && site.starts_with(|c: char| c.is_ascii_alphabetic())
+        && site.chars().all(|c| c.is_ascii_alphanumeric());
+    if valid {
+        Ok(site.to_string())
+    } else {
+        Err(format!("`{}` is not a four-character site ID such as KHTX", site))
+    }
+}
+
+fn check_date(year: &str, month: &str, day: &str) -> Result<String, String> {
+    let date = match (year.parse(), month.parse(), day.parse()) {
+        (Ok(y), Ok(m), Ok(d)) => NaiveDate::from_ymd_opt(y, m, d),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{}-{}-{} is not a valid date", year, month, day))?;
+
+    if date > chrono::Utc::now().date_naive() {
+        return Err(format!("{} is in the future", date));
+    }
+    Ok(date.to_string())
+}
+
+async fn check_index(url: &str) -> Result<String, String> {
+    let html = fetch_index(url).await.map_err(|e| format!("could not fetch {}: {}", url, e))?;
+    let extracted = extract_links(url, &Html::parse_document(&html)).map_err(|e| e.to_string())?;
+    match extracted.links.len() {
+        0 => Err(format!("{} lists no files for this site and date", url)),
+        count => Ok(format!("{} files listed", count)),
+    }
+}
+
+fn check_links_file(path: &str) -> Result<String, String> {
+    match links_file::read_links(path) {
+        Ok(links) if links.is_empty() => Err(format!("{} contains no downloadable URLs", path)),
+        Ok(links) => Ok(format!("{} URLs", links.len())),
+        Err(e) => Err(format!("could not read {}: {}", path, e)),
+    }
+}
+
+/// The output directory if it exists, otherwise the closest ancestor that
+/// does, which is where it would be created.
+fn nearest_existing_dir(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .unwrap_or(Path::new("."))
+}
+
+fn check_writable(output_dir: &str, existing: &Path) -> Result<String, String> {
+    let probe = existing.join(format!(".radar-fetcher-preflight-{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| format!("cannot write to {}: {}", existing.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    if Path::new(output_dir).is_dir() {
+        Ok(format!("{} is writable", output_dir))
+    } else {
+        Ok(format!("{} can be created in {}", output_dir, existing.display()))
+    }
+}
+
+fn check_space(dir: &Path, min_free: Option<u64>) -> Result<String, String> {
+    let available = fs2::available_space(dir).map_err(|e| format!("cannot read free space: {}", e))?;
+    match min_free {
+        Some(min_free) if available < min_free => Err(format!(
+            "{} free, below --min-free-space {}",
+            format_bytes(available),
+            format_bytes(min_free)
+        )),
+        _ => Ok(format!("{} free", format_bytes(available))),
+    }
+}
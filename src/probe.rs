@@ -0,0 +1,76 @@
+use futures::stream::{self, StreamExt};
+use scraper::Html;
+use serde_json::json;
+use url::Url;
+
+use crate::cli::{DateRange, OutputFormat};
+use crate::{extract_links, fetch_index, index_url};
+
+/// How many index pages are fetched at once while probing.
+const PROBE_CONCURRENCY: usize = 4;
+
+struct DayAvailability {
+    date: chrono::NaiveDate,
+    files: Result<usize, String>,
+}
+
+async fn probe_day(base_url: &Url, radar: &str, date: chrono::NaiveDate) -> DayAvailability {
+    let url = index_url(
+        base_url,
+        radar,
+        &date.format("%Y").to_string(),
+        &date.format("%m").to_string(),
+        &date.format("%d").to_string(),
+    );
+    let files = async {
+        let html = fetch_index(&url).await?;
+        let document = Html::parse_document(&html);
+        let extracted = extract_links(&url, &document)?;
+        Ok::<_, Box<dyn std::error::Error>>(extracted.links.len())
+    }
+    .await
+    .map_err(|e| e.to_string());
+
+    DayAvailability { date, files }
+}
+
+/// Fetches the index for every date in `range` and reports how many files
+/// each one lists, without downloading anything.
+pub async fn probe_date_availability(base_url: &Url, radar: &str, range: DateRange, format: OutputFormat) {
+    let results: Vec<DayAvailability> = stream::iter(range.days())
+        .map(|date| probe_day(base_url, radar, date))
+        .buffered(PROBE_CONCURRENCY)
+        .collect()
+        .await;
+
+    match format {
+        OutputFormat::Human => {
+            println!("Data availability for {} ({} to {}):", radar, range.start, range.end);
+            for day in &results {
+                match &day.files {
+                    Ok(0) => println!("  {}  {:>5} files  (no data)", day.date, 0),
+                    Ok(count) => println!("  {}  {:>5} files", day.date, count),
+                    Err(e) => println!("  {}  error: {}", day.date, e),
+                }
+            }
+            let available = results.iter().filter(|day| matches!(day.files, Ok(n) if n > 0)).count();
+            println!("{} of {} days have data", available, results.len());
+        }
+        OutputFormat::Json => {
+            let days: Vec<_> = results
+                .iter()
+                .map(|day| match &day.files {
+                    Ok(count) => json!({ "date": day.date.to_string(), "files": count }),
+                    Err(e) => json!({ "date": day.date.to_string(), "files": null, "error": e }),
+                })
+                .collect();
+            let report = json!({
+                "site": radar,
+                "start": range.start.to_string(),
+                "end": range.end.to_string(),
+                "days": days,
+            });
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
+    }
+}
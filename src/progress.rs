@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Receives events from `download_files` as a batch progresses.
+///
+/// Methods are called from many download tasks at once, so implementations
+/// need their own synchronization. To plug in a custom reporter, implement
+/// the trait and pass it in place of [`ConsoleProgress`]:
+///
+/// ```ignore
+/// struct CountingProgress(AtomicUsize);
+///
+/// impl Progress for CountingProgress {
+///     fn on_start(&self, _total: usize) {}
+///     fn on_file_done(&self, _filename: &str, _bytes: u64) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+///     fn on_error(&self, _url: &str, _error: &str) {}
+///     fn on_finish(&self, _downloaded: usize, _failed: usize) {}
+/// }
+/// ```
+pub trait Progress: Send + Sync {
+    /// Called once before any downloads begin.
+    fn on_start(&self, total: usize);
+    /// Called after each file is written to disk.
+    fn on_file_done(&self, filename: &str, bytes: u64);
+    /// Called when a download fails.
+    fn on_error(&self, url: &str, error: &str);
+    /// Called once after every download has either finished or failed.
+    fn on_finish(&self, downloaded: usize, failed: usize);
+}
+
+/// Prints a single self-overwriting progress line to the terminal.
+#[derive(Debug, Default)]
+pub struct ConsoleProgress {
+    total: AtomicUsize,
+    current: AtomicUsize,
+    /// Minimum time between redraws; `None` redraws on every file
+    min_redraw_interval: Option<Duration>,
+    redraw: Mutex<RedrawState>,
+}
+
+#[derive(Debug, Default)]
+struct RedrawState {
+    last_drawn: Option<Instant>,
+    /// The latest update that was skipped to respect the refresh rate
+    pending: Option<(usize, String)>,
+}
+
+impl ConsoleProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redraws at most `per_second` times a second. The final state is
+    /// always drawn.
+    pub fn with_refresh_rate(per_second: f64) -> Self {
+        Self {
+            min_redraw_interval: Some(Duration::from_secs_f64(1.0 / per_second)),
+            ..Self::default()
+        }
+    }
+
+    fn print_progress(&self, current: usize, filename: &str) {
+        let total = self.total.load(Ordering::SeqCst);
+        let percentage = (current as f64 / total as f64) * 100.0;
+        print!("\rDownloading Files: {}/{} ({:.1}%) | Last: {} ",
+               current, total, percentage, filename);
+        io::stdout().flush().unwrap();
+
+        if current == total {
+            println!("\nDownload Complete!");
+        }
+    }
+}
+
+impl Progress for ConsoleProgress {
+    fn on_start(&self, total: usize) {
+        self.total.store(total, Ordering::SeqCst);
+    }
+
+    fn on_file_done(&self, filename: &str, _bytes: u64) {
+        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        let Some(interval) = self.min_redraw_interval else {
+            self.print_progress(current, filename);
+            return;
+        };
+
+        let mut redraw = self.redraw.lock().unwrap();
+        let due = redraw.last_drawn.is_none_or(|drawn| drawn.elapsed() >= interval);
+        if due || current == self.total.load(Ordering::SeqCst) {
+            redraw.last_drawn = Some(Instant::now());
+            redraw.pending = None;
+            self.print_progress(current, filename);
+        } else {
+            redraw.pending = Some((current, filename.to_string()));
+        }
+    }
+
+    fn on_error(&self, url: &str, error: &str) {
+        eprintln!("\nError downloading {}: {}", url, error);
+    }
+
+    fn on_finish(&self, _downloaded: usize, _failed: usize) {
+        if let Some((current, filename)) = self.redraw.lock().unwrap().pending.take() {
+            self.print_progress(current, &filename);
+        }
+        println!(); // New line after progress
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use scraper::Html;
+use serde_json::json;
+
+use crate::cli::OutputFormat;
+use crate::{extract_links, fetch_index, filename_from_url};
+
+/// Re-fetches the index at `radar_url` and compares the files it lists
+/// against what is in `output_dir`. With `decoded_extension`, a decoder
+/// output `<file>.<ext>` counts as having `<file>`, whose raw copy may have
+/// been removed by `--discard-raw`.
+pub async fn reconcile(
+    radar_url: &str,
+    output_dir: &Path,
+    decoded_extension: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let html = fetch_index(radar_url).await?;
+    let document = Html::parse_document(&html);
+    let remote: BTreeSet<String> = extract_links(radar_url, &document)?
+        .links
+        .iter()
+        .map(|link| filename_from_url(link))
+        .collect();
+
+    let mut local = BTreeSet::new();
+    for entry in std::fs::read_dir(output_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            local.insert(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    // --per-file-metadata sidecars aren't listed remotely
+    local.retain(|name| {
+        !name
+            .strip_suffix(".json")
+            .is_some_and(|data_file| remote.contains(data_file))
+    });
+
+    if let Some(extension) = decoded_extension {
+        let suffix = format!(".{}", extension);
+        let decoded: Vec<String> = local
+            .iter()
+            .filter_map(|name| name.strip_suffix(&suffix))
+            .filter(|data_file| remote.contains(*data_file))
+            .map(str::to_string)
+            .collect();
+        for data_file in decoded {
+            local.remove(&format!("{}{}", data_file, suffix));
+            local.insert(data_file);
+        }
+    }
+
+    let missing_locally: Vec<&String> = remote.difference(&local).collect();
+    let missing_remotely: Vec<&String> = local.difference(&remote).collect();
+
+    match format {
+        OutputFormat::Human => {
+            println!(
+                "Reconcile: {} remote, {} local, {} missing locally, {} not listed remotely",
+                remote.len(),
+                local.len(),
+                missing_locally.len(),
+                missing_remotely.len()
+            );
+            for name in &missing_locally {
+                println!("  missing locally: {}", name);
+            }
+            for name in &missing_remotely {
+                println!("  not listed remotely: {}", name);
+            }
+        }
+        OutputFormat::Json => {
+            let report = json!({
+                "remote_files": remote.len(),
+                "local_files": local.len(),
+                "missing_locally": missing_locally,
+                "missing_remotely": missing_remotely,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+/// Where the Storm Prediction Center publishes daily storm reports. Each
+/// file covers the convective day starting at 12Z on its date.
+const SPC_REPORTS_URL: &str = "https://www.spc.noaa.gov/climo/reports";
+
+/// Downloads the SPC filtered storm reports CSV for `date` into a `reports/`
+/// subdirectory of `output_dir`, returning the saved path.
+///
+/// The reports cover the whole CONUS; they aren't narrowed to the radar's
+/// surroundings.
+pub async fn fetch_storm_reports(output_dir: &Path, date: NaiveDate) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let filename = format!("{}_rpts_filtered.csv", date.format("%y%m%d"));
+    let url = format!("{}/{}", SPC_REPORTS_URL, filename);
+
+    let client = crate::connections::client_builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let body = {
+        let _connection = crate::connections::acquire().await;
+        client.get(&url).send().await?.error_for_status()?.bytes().await?
+    };
+
+    let reports_dir = output_dir.join("reports");
+    tokio::fs::create_dir_all(&reports_dir).await?;
+    let path = reports_dir.join(filename);
+    crate::ensure_writable_target(&path).await?;
+    tokio::fs::write(&path, &body).await?;
+    Ok(path)
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use tokio::sync::Mutex;
+use url::Url;
+
+/// Product token matched against `User-agent` lines, alongside `*`.
+const ROBOTS_AGENT: &str = "radar-fetcher";
+
+/// The `Allow`/`Disallow` rules that apply to us on one host.
+#[derive(Debug, Default)]
+struct Rules {
+    /// `(allow, pattern)` pairs
+    rules: Vec<(bool, String)>,
+}
+
+impl Rules {
+    /// Parses the groups in a robots.txt that name us, falling back to the
+    /// `*` group if none do.
+    fn parse(body: &str) -> Self {
+        let mut ours = Vec::new();
+        let mut wildcard = Vec::new();
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (field, value) = (field.trim().to_ascii_lowercase(), value.trim());
+            match field.as_str() {
+                "user-agent" => {
+                    // A user-agent line after rules starts a new group
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (field == "allow", value.to_string());
+                    if agents.iter().any(|agent| agent == ROBOTS_AGENT) {
+                        ours.push(rule.clone());
+                    }
+                    if agents.iter().any(|agent| agent == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self { rules: if ours.is_empty() { wildcard } else { ours } }
+    }
+
+    /// The longest matching rule wins; ties go to `Allow`.
+    fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+/// Matches a robots.txt path pattern, supporting `*` wildcards and a
+/// trailing `$` anchor.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let Some(mut rest) = path.strip_prefix(parts[0]) else {
+        return false;
+    };
+    let Some((last, middle)) = parts[1..].split_last() else {
+        return !anchored || rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    if anchored { rest.ends_with(last) } else { rest.contains(last) }
+}
+
+/// Fetches each host's robots.txt once per run and answers whether a URL
+/// may be crawled.
+#[derive(Default)]
+pub struct RobotsCache {
+    hosts: Mutex<HashMap<String, Rules>>,
+}
+
+impl RobotsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn allows(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return true;
+        };
+        let origin = url.origin().ascii_serialization();
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        let mut hosts = self.hosts.lock().await;
+        if !hosts.contains_key(&origin) {
+            let rules = fetch_rules(&origin).await;
+            hosts.insert(origin.clone(), rules);
+        }
+        hosts[&origin].allows(&path)
+    }
+}
+
+/// A missing or unreadable robots.txt allows everything.
+async fn fetch_rules(origin: &str) -> Rules {
+    let body = async {
+        let client = crate::connections::client_builder()
+            .user_agent(crate::USER_AGENT)
+            .build()?;
+        let _connection = crate::connections::acquire().await;
+        client
+            .get(format!("{}/robots.txt", origin))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    };
+    match body.await {
+        Ok(body) => Rules::parse(&body),
+        Err(_) => Rules::default(),
+    }
+}
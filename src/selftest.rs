@@ -0,0 +1,183 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use scraper::Html;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::{DownloadOptions, download_files, extract_links, fetch_index, index_url};
+
+/// Files the fixture server lists and serves.
+const FIXTURE_FILES: [&str; 3] = [
+    "KTST20250315_000412_V06",
+    "KTST20250315_001003_V06",
+    "KTST20250315_001554_V06",
+];
+
+/// Deterministic content for a fixture file, so downloads can be checked
+/// byte for byte.
+fn fixture_body(name: &str) -> Vec<u8> {
+    format!("AR2V0006.{}", name).bytes().cycle().take(4096).collect()
+}
+
+fn fixture_index() -> String {
+    let rows: String = FIXTURE_FILES
+        .iter()
+        .map(|name| format!("<tr><td><a href=\"/data/{0}\">{0}</a></td></tr>\n", name))
+        .collect();
+    format!("<html><body><table>\n{}</table></body></html>", rows)
+}
+
+/// What a loopback server sends back for one request.
+pub struct Reply {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+/// Serves HTTP on a free loopback port until the returned task is aborted,
+/// answering each request with `respond(path)`.
+pub async fn serve_loopback<F>(respond: F) -> std::io::Result<(SocketAddr, JoinHandle<()>)>
+where
+    F: Fn(&str) -> Reply + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let respond = Arc::new(respond);
+    let server = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let respond = Arc::clone(&respond);
+            tokio::spawn(async move { serve(stream, &*respond).await });
+        }
+    });
+    Ok((addr, server))
+}
+
+/// Answers one request per connection.
+async fn serve(mut stream: TcpStream, respond: &(dyn Fn(&str) -> Reply + Send + Sync)) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let Reply { status, content_type, body } = respond(path);
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+/// The fixture site: `/data/<name>` is the fixture file, anything else the
+/// index.
+fn fixture_reply(path: &str) -> Reply {
+    let (status, content_type, body) = match path.strip_prefix("/data/") {
+        Some(name) if FIXTURE_FILES.contains(&name) => ("200 OK", "application/octet-stream", fixture_body(name)),
+        Some(_) => ("404 Not Found", "text/plain", b"not found".to_vec()),
+        None => ("200 OK", "text/html", fixture_index().into_bytes()),
+    };
+    Reply { status, content_type, body }
+}
+
+struct Step {
+    name: &'static str,
+    result: Result<String, String>,
+}
+
+/// Runs index discovery, downloading and verification against an in-process
+/// fixture server, reporting each step. Fails if any step does.
+pub async fn self_test() -> Result<(), Box<dyn std::error::Error>> {
+    let (addr, server) = serve_loopback(fixture_reply).await?;
+    let base_url = Url::parse(&format!("http://{}/index", addr))?;
+
+    let output_dir = std::env::temp_dir().join(format!("radar-fetcher-self-test-{}", std::process::id()));
+    let steps = run_steps(&base_url, &output_dir).await;
+    server.abort();
+    let _ = std::fs::remove_dir_all(&output_dir);
+
+    println!("Self-test:");
+    for step in &steps {
+        match &step.result {
+            Ok(detail) => println!("  ok    {}: {}", step.name, detail),
+            Err(problem) => println!("  FAIL  {}: {}", step.name, problem),
+        }
+    }
+    if steps.iter().any(|step| step.result.is_err()) {
+        return Err("self-test failed".into());
+    }
+    println!("Self-test passed");
+    Ok(())
+}
+
+/// Runs the steps in order, stopping at the first failure since each one
+/// needs the last one's output.
+async fn run_steps(base_url: &Url, output_dir: &Path) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let url = index_url(base_url, "KTST", "2025", "03", "15");
+
+    let links = match discover(&url).await {
+        Ok(links) => {
+            steps.push(Step { name: "discover", result: Ok(format!("{} links from the fixture index", links.len())) });
+            links
+        }
+        Err(e) => {
+            steps.push(Step { name: "discover", result: Err(e) });
+            return steps;
+        }
+    };
+
+    let result = download(links, output_dir).await;
+    let failed = result.is_err();
+    steps.push(Step { name: "download", result });
+    if failed {
+        return steps;
+    }
+
+    steps.push(Step { name: "verify", result: verify(output_dir) });
+    steps
+}
+
+async fn discover(url: &str) -> Result<Vec<String>, String> {
+    let html = fetch_index(url).await.map_err(|e| format!("could not fetch the fixture index: {}", e))?;
+    let links = extract_links(url, &Html::parse_document(&html)).map_err(|e| e.to_string())?.links;
+    if links.len() != FIXTURE_FILES.len() {
+        return Err(format!("found {} links, expected {}", links.len(), FIXTURE_FILES.len()));
+    }
+    Ok(links)
+}
+
+async fn download(links: Vec<String>, output_dir: &Path) -> Result<String, String> {
+    std::fs::create_dir_all(output_dir).map_err(|e| format!("could not create {}: {}", output_dir.display(), e))?;
+    let options = DownloadOptions { max_concurrent: 2, task_buffer: 2, ..Default::default() };
+    let total = links.len();
+    let outcome = download_files(links, &output_dir.to_string_lossy(), options, None).await;
+    match outcome.downloaded.len() {
+        n if n == total => Ok(format!("{} files downloaded", n)),
+        n => Err(format!("{} of {} files failed to download", total - n, total)),
+    }
+}
+
+fn verify(output_dir: &Path) -> Result<String, String> {
+    for name in FIXTURE_FILES {
+        let path = output_dir.join(name);
+        let contents = std::fs::read(&path).map_err(|e| format!("could not read {}: {}", name, e))?;
+        if contents != fixture_body(name) {
+            return Err(format!("{} does not match what the server sent", name));
+        }
+    }
+    Ok(format!("{} files match the fixture byte for byte", FIXTURE_FILES.len()))
+}
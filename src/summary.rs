@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cli::SummaryFormat;
+
+/// What a download run did, printed once it finishes.
+pub struct RunSummary {
+    /// What was fetched: `KHTX 2025-03-15`, or the links file path
+    pub target: String,
+    /// The run's --tag, if given
+    pub tag: Option<String>,
+    pub downloaded: usize,
+    pub failed: usize,
+    /// How many of the failures were files abandoned at `--file-deadline`
+    pub deadline_exceeded: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub output_dir: String,
+    /// Set when the output directory was packed with --archive-on-complete
+    pub archive: Option<PathBuf>,
+    /// Per-host totals for --report-bandwidth-per-host; empty otherwise
+    pub hosts: Vec<HostUsage>,
+}
+
+/// Data pulled from a single host over the run.
+pub struct HostUsage {
+    pub host: String,
+    pub files: usize,
+    pub bytes: u64,
+    /// Sum of each file's transfer time. Transfers overlap, so this can exceed
+    /// the run's wall-clock time.
+    pub transfer_time: Duration,
+}
+
+impl HostUsage {
+    /// Average rate of a single transfer from this host, in bytes per second.
+    fn bytes_per_sec(&self) -> u64 {
+        let secs = self.transfer_time.as_secs_f64();
+        if secs > 0.0 { (self.bytes as f64 / secs) as u64 } else { 0 }
+    }
+}
+
+/// Totals `(host, bytes, transfer time)` per host, busiest host first.
+pub fn usage_by_host<'a>(files: impl IntoIterator<Item = (&'a str, u64, Duration)>) -> Vec<HostUsage> {
+    let mut by_host: BTreeMap<&str, HostUsage> = BTreeMap::new();
+    for (host, bytes, transfer_time) in files {
+        let usage = by_host.entry(host).or_insert_with(|| HostUsage {
+            host: host.to_string(),
+            files: 0,
+            bytes: 0,
+            transfer_time: Duration::ZERO,
+        });
+        usage.files += 1;
+        usage.bytes += bytes;
+        usage.transfer_time += transfer_time;
+    }
+    let mut hosts: Vec<HostUsage> = by_host.into_values().collect();
+    hosts.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+    hosts
+}
+
+impl RunSummary {
+    pub fn print(&self, format: SummaryFormat) {
+        match format {
+            SummaryFormat::Human => {
+                if let Some(tag) = &self.tag {
+                    println!("Tag: {}", tag);
+                }
+                println!("Total files downloaded: {}", self.downloaded);
+                if self.deadline_exceeded > 0 {
+                    println!("Files abandoned at deadline: {}", self.deadline_exceeded);
+                }
+                println!("Files saved in: {}", self.output_dir);
+                if let Some(archive) = &self.archive {
+                    println!("Archive: {}", archive.display());
+                }
+                if !self.hosts.is_empty() {
+                    println!("Bandwidth by host:");
+                    for usage in &self.hosts {
+                        println!(
+                            "  {}: {} files, {}, {:.1}s transferring, {}/s per transfer",
+                            usage.host,
+                            usage.files,
+                            format_bytes(usage.bytes),
+                            usage.transfer_time.as_secs_f64(),
+                            format_bytes(usage.bytes_per_sec())
+                        );
+                    }
+                }
+            }
+            SummaryFormat::Compact => {
+                print!(
+                    "radar-fetcher {}{}: {} ok, {} failed, {}, {}s",
+                    self.target,
+                    self.tag.as_ref().map(|tag| format!(" [{}]", tag)).unwrap_or_default(),
+                    self.downloaded,
+                    self.failed,
+                    format_bytes(self.bytes),
+                    self.elapsed.as_secs()
+                );
+                match &self.archive {
+                    Some(archive) => println!(", archive {}", archive.display()),
+                    None => println!(),
+                }
+            }
+        }
+    }
+}
+
+/// Formats a byte count with one decimal in the largest fitting unit, e.g. `1.2GB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
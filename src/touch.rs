@@ -0,0 +1,140 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use futures::stream::{self, StreamExt};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, HeaderName, LAST_MODIFIED, RANGE};
+use reqwest::{Client, Response, StatusCode};
+
+use crate::cli::ProbeMethod;
+use crate::filename_from_url;
+
+enum TouchResult {
+    Touched,
+    /// Local size differs from the server's, so the content has changed
+    SizeMismatch,
+    MissingLocally,
+    NoLastModified,
+    Failed(String),
+}
+
+/// Requests `url`'s headers without its body: with HEAD, with a GET of the
+/// first byte, or (for `Auto`) with HEAD falling back to the GET when the
+/// server answers 405 Method Not Allowed.
+async fn probe(client: &Client, url: &str, method: ProbeMethod) -> reqwest::Result<Response> {
+    let ranged_get = || client.get(url).header(RANGE, "bytes=0-0").send();
+    let response = match method {
+        ProbeMethod::Get => ranged_get().await?,
+        ProbeMethod::Head | ProbeMethod::Auto => {
+            let response = client.head(url).send().await?;
+            if method == ProbeMethod::Auto && response.status() == StatusCode::METHOD_NOT_ALLOWED {
+                ranged_get().await?
+            } else {
+                response
+            }
+        }
+    };
+    response.error_for_status()
+}
+
+/// The full size of the file behind a probe response. A ranged GET answered
+/// with 206 carries it in `Content-Range`, since its `Content-Length` is the
+/// one byte sent.
+fn remote_size(response: &Response) -> Option<u64> {
+    let headers = response.headers();
+    let header = |name: HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        return header(CONTENT_RANGE)
+            .and_then(|value| value.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse().ok());
+    }
+    header(CONTENT_LENGTH).and_then(|value| value.parse().ok())
+}
+
+async fn touch_one(client: &Client, url: &str, output_dir: &Path, method: ProbeMethod) -> TouchResult {
+    let path = output_dir.join(filename_from_url(url));
+    let local_size = match tokio::fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return TouchResult::MissingLocally,
+    };
+
+    let response = {
+        let _connection = crate::connections::acquire().await;
+        match probe(client, url, method).await {
+            Ok(response) => response,
+            Err(e) => return TouchResult::Failed(e.to_string()),
+        }
+    };
+    let headers = response.headers();
+
+    if remote_size(&response).is_some_and(|size| size != local_size) {
+        return TouchResult::SizeMismatch;
+    }
+
+    let Some(modified) = headers
+        .get(LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+    else {
+        return TouchResult::NoLastModified;
+    };
+    let modified: SystemTime = modified.into();
+
+    let result = tokio::task::spawn_blocking(move || {
+        std::fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(modified)
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => TouchResult::Touched,
+        Ok(Err(e)) => TouchResult::Failed(e.to_string()),
+        Err(e) => TouchResult::Failed(e.to_string()),
+    }
+}
+
+/// Sets the mtime of each already-downloaded file to the server's
+/// `Last-Modified`, without downloading anything. Files whose size no longer
+/// matches the server's are left alone, since their content has changed.
+pub async fn touch_existing(
+    links: &[String],
+    output_dir: &Path,
+    concurrency: usize,
+    method: ProbeMethod,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = crate::connections::client_builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+
+    let results: Vec<(&String, TouchResult)> = stream::iter(links)
+        .map(|url| {
+            let client = &client;
+            async move { (url, touch_one(client, url, output_dir, method).await) }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let (mut touched, mut changed, mut missing, mut undated, mut failed) = (0, 0, 0, 0, 0);
+    for (url, result) in &results {
+        match result {
+            TouchResult::Touched => touched += 1,
+            TouchResult::SizeMismatch => {
+                println!("Size differs from server, not touching: {}", filename_from_url(url));
+                changed += 1;
+            }
+            TouchResult::MissingLocally => missing += 1,
+            TouchResult::NoLastModified => undated += 1,
+            TouchResult::Failed(e) => {
+                eprintln!("Error touching {}: {}", url, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Touched {} files ({} changed on server, {} not downloaded, {} without Last-Modified, {} failed)",
+        touched, changed, missing, undated, failed
+    );
+    Ok(())
+}